@@ -0,0 +1,31 @@
+/*!
+# HTMinL C ABI: Build
+*/
+
+use std::path::PathBuf;
+
+
+
+/// # Build.
+///
+/// Generate the C header for the ABI into `OUT_DIR`.
+///
+/// The committed copy — `include/htminl.h` — is checked against this by the
+/// test suite, and can be refreshed with `just capi-header`.
+fn main() {
+	println!("cargo:rerun-if-changed=build.rs");
+	println!("cargo:rerun-if-changed=cbindgen.toml");
+	println!("cargo:rerun-if-changed=src/lib.rs");
+
+	let dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").expect("Missing CARGO_MANIFEST_DIR."));
+	let out = PathBuf::from(std::env::var("OUT_DIR").expect("Missing OUT_DIR."));
+	let config = cbindgen::Config::from_file(dir.join("cbindgen.toml"))
+		.expect("Unable to parse cbindgen.toml.");
+
+	cbindgen::Builder::new()
+		.with_config(config)
+		.with_src(dir.join("src/lib.rs"))
+		.generate()
+		.expect("Unable to generate the C header.")
+		.write_to_file(out.join("htminl.h"));
+}
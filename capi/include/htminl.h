@@ -0,0 +1,105 @@
+/* HTMinL: C ABI. This file is generated; do not edit it by hand. */
+
+#ifndef HTMINL_H
+#define HTMINL_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ # Status.
+
+ This mirrors `MinifyError`, with a few extra variants for success, bad
+ arguments, and panics.
+ */
+typedef enum HtminlStatus {
+  /*
+   # Success.
+   */
+  HTMINL_STATUS_OK = 0,
+  /*
+   # Invalid Argument(s).
+
+   A required pointer was null.
+   */
+  HTMINL_STATUS_NULL = 1,
+  /*
+   # Invalid UTF-8.
+   */
+  HTMINL_STATUS_ENCODING = 2,
+  /*
+   # Parse Failure.
+   */
+  HTMINL_STATUS_PARSE = 3,
+  /*
+   # Serialization Failure.
+   */
+  HTMINL_STATUS_SERIALIZE = 4,
+  /*
+   # Write Failure.
+   */
+  HTMINL_STATUS_WRITE = 5,
+  /*
+   # Panic.
+
+   Something went horribly wrong inside the library.
+   */
+  HTMINL_STATUS_PANIC = 6,
+} HtminlStatus;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ # Minify.
+
+ Minify the `len` bytes of (UTF-8) HTML at `src` using the default
+ options, storing a pointer to the result in `out` and its length in
+ `out_len`.
+
+ The result is always written on success, even if it is the same as the
+ original. It is owned by the caller, and must be released with
+ `htminl_free`.
+
+ On failure, `out` and `out_len` are set to null and zero, respectively.
+
+ # Safety
+
+ `src` must point to at least `len` readable bytes, and `out` and `out_len`
+ must be valid for writes.
+ */
+enum HtminlStatus htminl_minify(const uint8_t *src, size_t len, uint8_t **out, size_t *out_len);
+
+/*
+ # Free.
+
+ Release a result previously returned by `htminl_minify`. Null pointers
+ are ignored.
+
+ # Safety
+
+ `ptr` and `len` must be exactly as returned by `htminl_minify`, and must
+ not be freed more than once.
+ */
+void htminl_free(uint8_t *ptr, size_t len);
+
+/*
+ # Last Error.
+
+ Return a description of the most recent failure on the current thread
+ as a NUL-terminated string, or null if the last call succeeded.
+
+ The string is owned by the library, and remains valid until the next
+ call to `htminl_minify` on the same thread.
+ */
+const char *htminl_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* HTMINL_H */
@@ -0,0 +1,393 @@
+/*!
+# HTMinL: C ABI.
+
+This crate exposes a tiny C interface for embedding the `htminl` library in
+non-Rust programs — PHP, Python, etc. — via a `cdylib`. The corresponding
+header is generated at build time, with a copy committed to
+`include/htminl.h`.
+
+The usage is simple: pass the source to `htminl_minify`, and if it returns
+`HTMINL_STATUS_OK`, do something with the output, then hand it back to
+`htminl_free`. If it returns anything else, `htminl_last_error` will
+explain why.
+
+Panics are caught at the boundary and reported as `HTMINL_STATUS_PANIC`
+rather than taking down the host process.
+*/
+
+#![deny(
+	clippy::allow_attributes_without_reason,
+	clippy::correctness,
+	unreachable_pub,
+)]
+
+#![warn(
+	clippy::complexity,
+	clippy::nursery,
+	clippy::pedantic,
+	clippy::perf,
+	clippy::style,
+
+	clippy::allow_attributes,
+	clippy::clone_on_ref_ptr,
+	clippy::create_dir,
+	clippy::filetype_is_file,
+	clippy::format_push_string,
+	clippy::get_unwrap,
+	clippy::impl_trait_in_params,
+	clippy::implicit_clone,
+	clippy::lossy_float_literal,
+	clippy::missing_assert_message,
+	clippy::missing_docs_in_private_items,
+	clippy::needless_raw_strings,
+	clippy::panic_in_result_fn,
+	clippy::pub_without_shorthand,
+	clippy::rest_pat_in_fully_bound_structs,
+	clippy::semicolon_inside_block,
+	clippy::str_to_string,
+	clippy::todo,
+	clippy::undocumented_unsafe_blocks,
+	clippy::unneeded_field_pattern,
+	clippy::unseparated_literal_suffix,
+	clippy::unwrap_in_result,
+
+	macro_use_extern_crate,
+	missing_copy_implementations,
+	missing_docs,
+	non_ascii_idents,
+	trivial_casts,
+	trivial_numeric_casts,
+	unused_extern_crates,
+	unused_import_braces,
+)]
+
+#![expect(clippy::doc_markdown, reason = "HTMinL makes this annoying.")]
+#![expect(unsafe_code, reason = "It's an FFI.")]
+
+use htminl::MinifyError;
+use std::{
+	any::Any,
+	cell::RefCell,
+	ffi::{
+		c_char,
+		CStr,
+		CString,
+	},
+	panic::AssertUnwindSafe,
+	ptr,
+};
+
+
+
+thread_local! {
+	/// # Last Error.
+	///
+	/// The details of the most recent failure on the current thread, if any.
+	static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+/// # Status.
+///
+/// This mirrors `MinifyError`, with a few extra variants for success, bad
+/// arguments, and panics.
+pub enum HtminlStatus {
+	/// # Success.
+	Ok = 0,
+
+	/// # Invalid Argument(s).
+	///
+	/// A required pointer was null.
+	Null = 1,
+
+	/// # Invalid UTF-8.
+	Encoding = 2,
+
+	/// # Parse Failure.
+	Parse = 3,
+
+	/// # Serialization Failure.
+	Serialize = 4,
+
+	/// # Write Failure.
+	Write = 5,
+
+	/// # Panic.
+	///
+	/// Something went horribly wrong inside the library.
+	Panic = 6,
+}
+
+impl From<MinifyError> for HtminlStatus {
+	fn from(err: MinifyError) -> Self {
+		match err {
+			MinifyError::Encoding => Self::Encoding,
+			MinifyError::Parse(_) => Self::Parse,
+			MinifyError::Serialize => Self::Serialize,
+			MinifyError::Write(_) => Self::Write,
+		}
+	}
+}
+
+
+
+#[unsafe(no_mangle)]
+/// # Minify.
+///
+/// Minify the `len` bytes of (UTF-8) HTML at `src` using the default
+/// options, storing a pointer to the result in `out` and its length in
+/// `out_len`.
+///
+/// The result is always written on success, even if it is the same as the
+/// original. It is owned by the caller, and must be released with
+/// `htminl_free`.
+///
+/// On failure, `out` and `out_len` are set to null and zero, respectively.
+///
+/// # Safety
+///
+/// `src` must point to at least `len` readable bytes, and `out` and `out_len`
+/// must be valid for writes.
+pub unsafe extern "C" fn htminl_minify(
+	src: *const u8,
+	len: usize,
+	out: *mut *mut u8,
+	out_len: *mut usize,
+) -> HtminlStatus {
+	// Safety: the caller's promises carry over.
+	guard(|| unsafe { minify(src, len, out, out_len) })
+}
+
+
+#[unsafe(no_mangle)]
+/// # Free.
+///
+/// Release a result previously returned by `htminl_minify`. Null pointers
+/// are ignored.
+///
+/// # Safety
+///
+/// `ptr` and `len` must be exactly as returned by `htminl_minify`, and must
+/// not be freed more than once.
+pub unsafe extern "C" fn htminl_free(ptr: *mut u8, len: usize) {
+	if ! ptr.is_null() {
+		// Safety: the caller promises this came from `htminl_minify`, which
+		// leaked it from a boxed slice of the same length.
+		drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(ptr, len)) });
+	}
+}
+
+#[unsafe(no_mangle)]
+/// # Last Error.
+///
+/// Return a description of the most recent failure on the current thread
+/// as a NUL-terminated string, or null if the last call succeeded.
+///
+/// The string is owned by the library, and remains valid until the next
+/// call to `htminl_minify` on the same thread.
+pub extern "C" fn htminl_last_error() -> *const c_char {
+	LAST_ERROR.with_borrow(|v| v.as_deref().map_or(ptr::null(), CStr::as_ptr))
+}
+
+
+
+/// # Fail.
+///
+/// Record the error details for `htminl_last_error` and return the status.
+fn fail(status: HtminlStatus, msg: String) -> HtminlStatus {
+	let msg = CString::new(msg).unwrap_or_default();
+	LAST_ERROR.with_borrow_mut(|v| { v.replace(msg); });
+	status
+}
+
+/// # Guard.
+///
+/// Run `cb`, catching any panics so they don't unwind into foreign code,
+/// which would abort the host process.
+fn guard<F>(cb: F) -> HtminlStatus
+where F: FnOnce() -> HtminlStatus {
+	std::panic::catch_unwind(AssertUnwindSafe(cb)).unwrap_or_else(|e|
+		fail(HtminlStatus::Panic, format!("The minifier panicked: {}", panic_message(&*e)))
+	)
+}
+
+/// # Minify (Unguarded).
+///
+/// This is the actual implementation of `htminl_minify`.
+///
+/// # Safety
+///
+/// Same as `htminl_minify`.
+unsafe fn minify(
+	src: *const u8,
+	len: usize,
+	out: *mut *mut u8,
+	out_len: *mut usize,
+) -> HtminlStatus {
+	if out.is_null() || out_len.is_null() {
+		return fail(HtminlStatus::Null, "A required argument was null.".to_owned());
+	}
+
+	// Safety: the pointers were checked for null, and the caller promises
+	// they're otherwise valid.
+	unsafe {
+		*out = ptr::null_mut();
+		*out_len = 0;
+	}
+
+	if src.is_null() {
+		return fail(HtminlStatus::Null, "A required argument was null.".to_owned());
+	}
+
+	// Safety: the caller promises `src` has at least `len` bytes.
+	let src = unsafe { std::slice::from_raw_parts(src, len) };
+	match htminl::minify_bytes(src) {
+		Ok(res) => {
+			let res = res.into_boxed_slice();
+			let res_len = res.len();
+
+			// Safety: see above.
+			unsafe {
+				*out = Box::into_raw(res).cast::<u8>();
+				*out_len = res_len;
+			}
+
+			LAST_ERROR.with_borrow_mut(|v| { v.take(); });
+			HtminlStatus::Ok
+		},
+		Err(e) => fail(HtminlStatus::from(e), e.to_string()),
+	}
+}
+
+/// # Panic Message.
+fn panic_message(err: &(dyn Any + Send)) -> &str {
+	err.downcast_ref::<&str>().copied()
+		.or_else(|| err.downcast_ref::<String>().map(String::as_str))
+		.unwrap_or("unknown cause")
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// # Minify Via the C ABI.
+	fn minify(src: &[u8]) -> (HtminlStatus, Option<Vec<u8>>, Option<String>) {
+		let mut out: *mut u8 = ptr::null_mut();
+		let mut out_len = 0_usize;
+		// Safety: the pointers are all valid.
+		let status = unsafe { htminl_minify(src.as_ptr(), src.len(), &raw mut out, &raw mut out_len) };
+
+		let res =
+			if out.is_null() { None }
+			else {
+				// Safety: the output is non-null, so should have this length.
+				let res = unsafe { std::slice::from_raw_parts(out, out_len) }.to_vec();
+
+				// Safety: it came from htminl_minify, and won't be used again.
+				unsafe { htminl_free(out, out_len); }
+				Some(res)
+			};
+
+		let err = htminl_last_error();
+		let err =
+			if err.is_null() { None }
+			else {
+				// Safety: non-null errors are NUL-terminated strings.
+				Some(unsafe { CStr::from_ptr(err) }.to_string_lossy().into_owned())
+			};
+
+		(status, res, err)
+	}
+
+	#[test]
+	fn t_minify() {
+		assert_eq!(
+			minify(b"<p>  Hello  </p>"),
+			(HtminlStatus::Ok, Some(b"<p> Hello </p>".to_vec()), None),
+		);
+
+		// Unchanged and empty are fine too.
+		assert_eq!(
+			minify(b"<p>Hello</p>"),
+			(HtminlStatus::Ok, Some(b"<p>Hello</p>".to_vec()), None),
+		);
+		assert_eq!(minify(b""), (HtminlStatus::Ok, Some(Vec::new()), None));
+
+		// Bad UTF-8.
+		assert_eq!(
+			minify(b"<p>\xFF</p>"),
+			(
+				HtminlStatus::Encoding,
+				None,
+				Some(MinifyError::Encoding.as_str().to_owned()),
+			),
+		);
+
+		// The error should clear after a success.
+		assert_eq!(minify(b"<p>Hi</p>").2, None);
+	}
+
+	#[test]
+	fn t_null() {
+		let mut out: *mut u8 = ptr::null_mut();
+		let mut out_len = 0_usize;
+		assert_eq!(
+			// Safety: null is invalid, but should be caught.
+			unsafe { htminl_minify(ptr::null(), 0, &raw mut out, &raw mut out_len) },
+			HtminlStatus::Null,
+		);
+		assert!(out.is_null());
+		assert!(! htminl_last_error().is_null());
+
+		assert_eq!(
+			// Safety: same as above.
+			unsafe { htminl_minify(b"<p></p>".as_ptr(), 7, ptr::null_mut(), &raw mut out_len) },
+			HtminlStatus::Null,
+		);
+
+		// Safety: freeing null is a no-op.
+		unsafe { htminl_free(ptr::null_mut(), 0); }
+	}
+
+	#[test]
+	fn t_panic() {
+		// Keep the test output clean.
+		let hook = std::panic::take_hook();
+		std::panic::set_hook(Box::new(|_| {}));
+		let status = guard(|| panic!("Boom!"));
+		std::panic::set_hook(hook);
+
+		assert_eq!(status, HtminlStatus::Panic);
+		let err = htminl_last_error();
+		assert!(! err.is_null());
+
+		// Safety: non-null errors are NUL-terminated strings.
+		let err = unsafe { CStr::from_ptr(err) };
+		assert_eq!(err.to_str(), Ok("The minifier panicked: Boom!"));
+
+		// Successes should still work afterwards.
+		assert_eq!(guard(|| HtminlStatus::Ok), HtminlStatus::Ok);
+	}
+
+	#[test]
+	fn t_header() {
+		let fresh = include_str!(concat!(env!("OUT_DIR"), "/htminl.h"));
+		let path = concat!(env!("CARGO_MANIFEST_DIR"), "/include/htminl.h");
+
+		// Refresh the committed copy if asked to.
+		if std::env::var_os("HTMINL_BLESS").is_some() {
+			std::fs::write(path, fresh).expect("Unable to write header.");
+		}
+
+		assert_eq!(
+			std::fs::read_to_string(path).ok().as_deref(),
+			Some(fresh),
+			"include/htminl.h is stale; run `just capi-header` to refresh it.",
+		);
+	}
+}
@@ -0,0 +1,216 @@
+/*!
+# HTMinL: Backups.
+*/
+
+use crate::{
+	ErrorContext,
+	HtminlError,
+	Mirror,
+};
+use std::{
+	path::{
+		Path,
+		PathBuf,
+	},
+};
+
+
+
+/// # Backup Extension.
+const EXT: &str = "orig";
+
+
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// # Backups.
+///
+/// This determines where the original copies of documents are saved before
+/// they're overwritten in-place, and hence where they're restored from.
+pub(super) enum Backup {
+	/// # Alongside the Originals.
+	///
+	/// The backup of `foo.html` is `foo.html.orig`.
+	Sibling,
+
+	/// # Mirror Into a Directory.
+	Dir(Mirror),
+}
+
+impl Backup {
+	/// # New Backup Directory.
+	///
+	/// Returns `None` if the directory cannot be created or canonicalized.
+	pub(super) fn dir(dst: &Path, roots: Vec<PathBuf>) -> Option<Self> {
+		Mirror::new(dst, roots).map(Self::Dir)
+	}
+
+	#[must_use]
+	/// # Backup Directory.
+	///
+	/// Return the backup directory, if any, so it can be excluded from the
+	/// search.
+	pub(super) fn backup_dir(&self) -> Option<&Path> {
+		if let Self::Dir(mirror) = self { Some(mirror.dir()) }
+		else { None }
+	}
+
+	#[must_use]
+	/// # Is Backup?
+	///
+	/// Returns `true` if `src` looks like a backup file and should therefore
+	/// be skipped.
+	pub(super) fn is_backup(&self, src: &Path) -> bool {
+		match self {
+			Self::Sibling => src.extension().is_some_and(|e| e.eq_ignore_ascii_case(EXT)),
+			Self::Dir(mirror) => src.starts_with(mirror.dir()),
+		}
+	}
+
+	#[must_use]
+	/// # Backup Path.
+	///
+	/// Return the backup path for `src`, or `None` if `src` doesn't fall
+	/// under any of the roots.
+	fn path(&self, src: &Path) -> Option<PathBuf> {
+		match self {
+			Self::Sibling => {
+				let mut name = src.file_name()?.to_os_string();
+				name.push(".");
+				name.push(EXT);
+				Some(src.with_file_name(name))
+			},
+			Self::Dir(mirror) => mirror.path(src),
+		}
+	}
+
+	/// # Save Backup.
+	///
+	/// Copy `src` — and its metadata — to its backup location.
+	///
+	/// If a backup already exists, it is only kept if `src` is still what
+	/// a previous run made of it, i.e. `minify(backup)`, so that repeated
+	/// runs hang onto the real original. If `src` has since been edited or
+	/// regenerated, the backup is refreshed.
+	///
+	/// ## Errors
+	///
+	/// This will return an error if the backup cannot be saved.
+	pub(super) fn save<F>(&self, src: &Path, minify: F) -> Result<(), HtminlError>
+	where F: FnOnce(String) -> Option<String> {
+		let dst = self.path(src).ok_or_else(|| HtminlError::Backup(
+			ErrorContext::new("The backup location could not be determined.").with_path(src)
+		))?;
+		if
+			let Ok(old) = std::fs::read_to_string(&dst) &&
+			let Some(old) = minify(old) &&
+			std::fs::read_to_string(src).is_ok_and(|cur| cur == old)
+		{
+			return Ok(());
+		}
+
+		write_atomic::copy_file(src, &dst)
+			.map_err(|e| HtminlError::Backup(ErrorContext::io(&dst, &e)))
+	}
+
+	/// # Restore Backup.
+	///
+	/// Move the backup of `src`, if any, back into place, returning `true`
+	/// if there was one.
+	///
+	/// ## Errors
+	///
+	/// This will return an error if the backup exists but cannot be moved
+	/// back.
+	pub(super) fn restore(&self, src: &Path) -> Result<bool, HtminlError> {
+		let Some(backup) = self.path(src).filter(|p| p.is_file()) else {
+			return Ok(false);
+		};
+
+		// Renaming is cheapest, but won't work across devices.
+		if std::fs::rename(&backup, src).is_err() {
+			write_atomic::copy_file(backup.as_path(), src)
+				.and_then(|()| std::fs::remove_file(&backup))
+				.map_err(|e| HtminlError::Restore(ErrorContext::io(&backup, &e)))?;
+		}
+
+		Ok(true)
+	}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn t_path() {
+		let src = Path::new("/src/site/blog/index.html");
+		assert_eq!(
+			Backup::Sibling.path(src).as_deref(),
+			Some(Path::new("/src/site/blog/index.html.orig")),
+		);
+
+		let backup = Backup::Dir(Mirror {
+			dst: PathBuf::from("/bak"),
+			roots: vec![PathBuf::from("/src/site")],
+		});
+		assert_eq!(
+			backup.path(src).as_deref(),
+			Some(Path::new("/bak/blog/index.html")),
+		);
+		assert_eq!(backup.path(Path::new("/elsewhere/index.html")), None);
+
+		// Backups.
+		assert!(Backup::Sibling.is_backup(Path::new("/src/index.html.orig")));
+		assert!(! Backup::Sibling.is_backup(src));
+		assert!(backup.is_backup(Path::new("/bak/blog/index.html")));
+		assert!(! backup.is_backup(src));
+	}
+
+	#[test]
+	fn t_save_restore() {
+		let dir = std::env::temp_dir().join(format!("htminl-backup-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).expect("Unable to create temp dir.");
+		let src = dir.join("index.html");
+		let bak = dir.join("index.html.orig");
+
+		// Pretend minification means uppercasing.
+		let minify = |s: String| Some(s.to_uppercase());
+
+		// Back it up.
+		std::fs::write(&src, b"original").expect("Unable to write source.");
+		Backup::Sibling.save(&src, minify).expect("Backup failed.");
+		assert_eq!(std::fs::read(&bak).ok().as_deref(), Some(&b"original"[..]));
+
+		// Existing backups should be left alone if the source is just the
+		// minified version of them.
+		std::fs::write(&src, b"ORIGINAL").expect("Unable to write source.");
+		Backup::Sibling.save(&src, minify).expect("Backup failed.");
+		assert_eq!(std::fs::read(&bak).ok().as_deref(), Some(&b"original"[..]));
+
+		// But refreshed if the source has moved on.
+		std::fs::write(&src, b"edited").expect("Unable to write source.");
+		Backup::Sibling.save(&src, minify).expect("Backup failed.");
+		assert_eq!(std::fs::read(&bak).ok().as_deref(), Some(&b"edited"[..]));
+
+		// Restore it.
+		std::fs::write(&src, b"EDITED").expect("Unable to write source.");
+		std::assert_matches!(Backup::Sibling.restore(&src), Ok(true));
+		assert_eq!(std::fs::read(&src).ok().as_deref(), Some(&b"edited"[..]));
+		assert!(! bak.exists(), "The backup should have been removed.");
+
+		// Nothing left to restore.
+		std::assert_matches!(Backup::Sibling.restore(&src), Ok(false));
+
+		// Failures should say where, and why.
+		let backup = Backup::Dir(Mirror { dst: src.clone(), roots: vec![dir.clone()] });
+		let err = backup.save(&src, minify).expect_err("Backup should have failed.");
+		std::assert_matches!(err, HtminlError::Backup(_));
+		let reason = err.reason(&src);
+		assert!(reason.contains("index.html/index.html: "), "{reason}");
+		assert!(err.context().is_some_and(|c| c.io_kind().is_some()), "Missing I/O error.");
+
+		let _res = std::fs::remove_dir_all(&dir);
+	}
+}
@@ -0,0 +1,399 @@
+/*!
+# HTMinL: Incremental Cache.
+*/
+
+use crate::{
+	HtminlError,
+	minify::Minified,
+	Output,
+	Precompress,
+	Savings,
+	Timings,
+};
+use std::{
+	borrow::Cow,
+	collections::HashMap,
+	fmt::Write,
+	num::NonZeroU64,
+	path::{
+		Path,
+		PathBuf,
+	},
+	sync::Mutex,
+	time::UNIX_EPOCH,
+};
+
+
+
+/// # Default Manifest Path.
+pub(super) const DEFAULT_PATH: &str = ".htminl-cache";
+
+/// # Manifest Header.
+///
+/// This is bumped whenever the format changes so old manifests are simply
+/// ignored.
+const HEADER: &str = "# htminl-cache v3";
+
+
+
+#[derive(Debug)]
+/// # Incremental Cache.
+///
+/// This keeps track of the documents HTMinL has already processed so that
+/// unchanged ones can be skipped on subsequent runs.
+///
+/// The manifest is a simple tab-separated text file with one line per
+/// source: the content hash, size, and modification time of the source
+/// (as it was left), the fingerprint of the settings it was minified with,
+/// whether it was a fragment, the precompressed siblings saved, and the
+/// source and destination paths.
+pub(super) struct Cache {
+	/// # Manifest Path.
+	src: PathBuf,
+
+	/// # Previous Entries.
+	old: HashMap<PathBuf, Entry>,
+
+	/// # Current Entries.
+	///
+	/// Sources processed during this run. `None` indicates the entry should
+	/// be dropped.
+	new: Mutex<HashMap<PathBuf, Option<Entry>>>,
+}
+
+impl Cache {
+	#[must_use]
+	/// # Load.
+	///
+	/// Load the manifest from `src`. If missing or invalid, the cache will
+	/// simply start out empty.
+	pub(super) fn load(src: PathBuf) -> Self {
+		let old = std::fs::read_to_string(&src)
+			.ok()
+			.filter(|raw| raw.lines().next() == Some(HEADER))
+			.map(|raw| raw.lines().skip(1).filter_map(Entry::from_line).collect())
+			.unwrap_or_default();
+
+		Self { src, old, new: Mutex::new(HashMap::new()) }
+	}
+
+	#[must_use]
+	/// # Check.
+	///
+	/// If `src` — and its destination — are unchanged since they were last
+	/// recorded, return the cached result.
+	///
+	/// The settings `config` fingerprint must match too, otherwise changes to
+	/// `htminl.toml`, the CLI flags, or HTMinL itself would go unnoticed.
+	///
+	/// Any precompressed siblings saved last time must also still exist,
+	/// and be at least as new as the destination.
+	///
+	/// Sizes and modification times are compared first; if the former match
+	/// but the latter don't, the contents are hashed to make sure.
+	pub(super) fn check(&self, src: &Path, output: &Output, config: u64)
+	-> Option<Minified> {
+		let entry = self.old.get(src)?;
+		if entry.config != config { return None; }
+		let dst = expected_dst(src, output)?;
+		if entry.dst != dst || (dst != src && ! dst.is_file()) { return None; }
+		if entry.siblings != Precompress::default() {
+			let dst_mtime = mtime(&std::fs::metadata(&dst).ok()?)?;
+			for sibling in entry.siblings.siblings(&dst) {
+				let sibling_mtime = mtime(&std::fs::metadata(&sibling).ok()?)?;
+				if sibling_mtime < dst_mtime { return None; }
+			}
+		}
+
+		let meta = std::fs::metadata(src).ok()?;
+		if meta.len() != entry.size { return None; }
+		if mtime(&meta) != Some(entry.mtime) {
+			let raw = std::fs::read(src).ok()?;
+			if xxhash_rust::xxh3::xxh3_64(&raw) != entry.hash { return None; }
+		}
+
+		// Still good! Carry it forward.
+		self.insert(src, Some(entry.clone()));
+		Some(Minified {
+			before: NonZeroU64::new(entry.size)?,
+			after: None,
+			fragment: entry.fragment,
+			savings: Savings::default(),
+			timings: Timings::default(),
+		})
+	}
+
+	/// # Update.
+	///
+	/// Record the current state of `src` after processing with the settings
+	/// fingerprinted by `config`, along with the precompressed `siblings`
+	/// saved. Failures, and dry-run documents that would have changed, are
+	/// dropped instead.
+	pub(super) fn update(
+		&self,
+		src: &Path,
+		output: &Output,
+		config: u64,
+		siblings: Precompress,
+		res: Option<&Minified>,
+	) {
+		let entry = res
+			.filter(|m| ! output.is_dry_run() || m.after.is_none())
+			.and_then(|m| Entry::new(
+				src,
+				expected_dst(src, output)?.into_owned(),
+				config,
+				m.fragment,
+				siblings,
+			));
+		self.insert(src, entry);
+	}
+
+	/// # Save.
+	///
+	/// Merge the current entries into the previous ones and save the
+	/// manifest, pruning any whose sources no longer exist.
+	///
+	/// ## Errors
+	///
+	/// This will return an error if the manifest cannot be saved.
+	pub(super) fn save(self) -> Result<(), HtminlError> {
+		let Self { src, mut old, new } = self;
+		let new = new.into_inner().map_err(|_| HtminlError::CacheSave)?;
+		for (k, v) in new {
+			if let Some(v) = v { old.insert(k, v); }
+			else { old.remove(&k); }
+		}
+
+		let mut entries: Vec<(PathBuf, Entry)> = old.into_iter()
+			.filter(|(k, _)| k.is_file())
+			.collect();
+		entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+		let mut out = String::with_capacity(HEADER.len() + 1 + entries.len() * 128);
+		out.push_str(HEADER);
+		out.push('\n');
+		for (k, v) in entries { v.push_line(&k, &mut out); }
+
+		write_atomic::write_file(&src, out.as_bytes()).map_err(|_| HtminlError::CacheSave)
+	}
+
+	/// # Insert.
+	fn insert(&self, src: &Path, entry: Option<Entry>) {
+		if let Ok(mut new) = self.new.lock() { new.insert(src.to_path_buf(), entry); }
+	}
+}
+
+
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// # Manifest Entry.
+struct Entry {
+	/// # Content Hash.
+	hash: u64,
+
+	/// # File Size.
+	size: u64,
+
+	/// # Modification Time (Nanoseconds Since Epoch).
+	mtime: u64,
+
+	/// # Settings Fingerprint.
+	config: u64,
+
+	/// # Fragment?
+	fragment: bool,
+
+	/// # Precompressed Siblings.
+	siblings: Precompress,
+
+	/// # Destination.
+	dst: PathBuf,
+}
+
+impl Entry {
+	/// # New.
+	///
+	/// Fingerprint `src` as it currently exists.
+	fn new(
+		src: &Path,
+		dst: PathBuf,
+		config: u64,
+		fragment: bool,
+		siblings: Precompress,
+	) -> Option<Self> {
+		let raw = std::fs::read(src).ok()?;
+		let mtime = mtime(&std::fs::metadata(src).ok()?)?;
+		Some(Self {
+			hash: xxhash_rust::xxh3::xxh3_64(&raw),
+			size: raw.len() as u64,
+			mtime,
+			config,
+			fragment,
+			siblings,
+			dst,
+		})
+	}
+
+	/// # From Manifest Line.
+	fn from_line(line: &str) -> Option<(PathBuf, Self)> {
+		let mut parts = line.split('\t');
+		let hash = u64::from_str_radix(parts.next()?, 16).ok()?;
+		let size = parts.next()?.parse::<u64>().ok()?;
+		let mtime = parts.next()?.parse::<u64>().ok()?;
+		let config = u64::from_str_radix(parts.next()?, 16).ok()?;
+		let fragment = match parts.next()? {
+			"0" => false,
+			"1" => true,
+			_ => return None,
+		};
+		let siblings = Precompress::from_manifest(parts.next()?)?;
+		let src = PathBuf::from(parts.next()?);
+		let dst = PathBuf::from(parts.next()?);
+		if parts.next().is_some() { return None; }
+
+		Some((src, Self { hash, size, mtime, config, fragment, siblings, dst }))
+	}
+
+	/// # Push Manifest Line.
+	///
+	/// Paths that can't be represented safely are silently skipped.
+	fn push_line(&self, src: &Path, out: &mut String) {
+		let (Some(src), Some(dst)) = (src.to_str(), self.dst.to_str()) else { return; };
+		if src.contains(['\t', '\n', '\r']) || dst.contains(['\t', '\n', '\r']) { return; }
+
+		let _res = writeln!(
+			out,
+			"{:016x}\t{}\t{}\t{:016x}\t{}\t{}\t{src}\t{dst}",
+			self.hash,
+			self.size,
+			self.mtime,
+			self.config,
+			u8::from(self.fragment),
+			self.siblings.as_str(),
+		);
+	}
+}
+
+
+
+/// # Expected Destination.
+///
+/// For dry runs, this is the source itself, since only documents that are
+/// already minified get cached.
+fn expected_dst<'a>(src: &'a Path, output: &Output) -> Option<Cow<'a, Path>> {
+	if output.is_dry_run() { Some(Cow::Borrowed(src)) }
+	else { output.dst(src) }
+}
+
+/// # Modification Time.
+fn mtime(meta: &std::fs::Metadata) -> Option<u64> {
+	let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+	u64::try_from(mtime.as_nanos()).ok()
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn t_line() {
+		let entry = Entry {
+			hash: 0xdead_beef,
+			size: 1234,
+			mtime: 1_700_000_000_000_000_000,
+			config: 0xcafe,
+			fragment: true,
+			siblings: Precompress::from_cli("gzip,brotli").expect("Invalid encodings."),
+			dst: PathBuf::from("/out/index.html"),
+		};
+
+		let mut out = String::new();
+		entry.push_line(Path::new("/src/index.html"), &mut out);
+		assert_eq!(
+			out,
+			"00000000deadbeef\t1234\t1700000000000000000\t000000000000cafe\t1\tgz,br\t/src/index.html\t/out/index.html\n",
+		);
+		assert_eq!(
+			Entry::from_line(out.trim_end()),
+			Some((PathBuf::from("/src/index.html"), entry.clone())),
+		);
+
+		// Unsafe paths are skipped.
+		out.clear();
+		entry.push_line(Path::new("/src/in\tdex.html"), &mut out);
+		assert!(out.is_empty());
+
+		// As is nonsense.
+		for i in [
+			"",
+			"xyz\t1\t1\t1\t0\t-\t/a\t/b",
+			"1\t1\t1\t1\t2\t-\t/a\t/b",
+			"1\t1\t1\tnope\t0\t-\t/a\t/b",
+			"1\t1\t1\t1\t0\tzstd\t/a\t/b",
+			"1\t1\t1\t1\t0\t-\t/a",
+			"1\t1\t1\t1\t0\t-\t/a\t/b\t/c",
+			"1\t1\t1\t1\t0\t/a\t/b", // v2.
+			"1\t1\t1\t0\t/a\t/b", // v1.
+		] {
+			assert!(Entry::from_line(i).is_none(), "{i:?}");
+		}
+	}
+
+	#[test]
+	fn t_check() {
+		let dir = std::env::temp_dir().join(format!("htminl-cache-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).expect("Unable to create temporary directory.");
+		let src = std::fs::canonicalize(&dir).expect("Canonicalization failed.").join("index.html");
+		std::fs::write(&src, "<p>Hi</p>").expect("Write failed.");
+
+		let minified = Minified {
+			before: NonZeroU64::MIN,
+			after: None,
+			fragment: true,
+			savings: Savings::default(),
+			timings: Timings::default(),
+		};
+
+		// Record it, then load it back.
+		let cache = Cache::load(dir.join("manifest"));
+		cache.update(&src, &Output::InPlace, 1, Precompress::default(), Some(&minified));
+		cache.save().expect("Cache save failed.");
+		let cache = Cache::load(dir.join("manifest"));
+
+		// Same settings, same file: hit.
+		assert!(cache.check(&src, &Output::InPlace, 1).is_some());
+
+		// Different settings: miss.
+		assert!(cache.check(&src, &Output::InPlace, 2).is_none());
+
+		// Switching on precompression changes the settings too.
+		let res = crate::config::Resolved::default();
+		let opts = res.options();
+		let plain = res.fingerprint(&opts, None, crate::Preserve::default());
+		let gzip = Precompress::from_cli("gzip");
+		let gzipped = res.fingerprint(&opts, gzip, crate::Preserve::default());
+		let cache = Cache::load(dir.join("manifest"));
+		cache.update(&src, &Output::InPlace, plain, Precompress::default(), Some(&minified));
+		cache.save().expect("Cache save failed.");
+		let cache = Cache::load(dir.join("manifest"));
+		assert!(cache.check(&src, &Output::InPlace, plain).is_some());
+		assert!(cache.check(&src, &Output::InPlace, gzipped).is_none());
+
+		// Once the siblings are saved, they have to stick around.
+		let gzip = gzip.expect("Invalid encodings.");
+		let gz = src.with_extension("html.gz");
+		std::fs::write(&gz, b"gzipped").expect("Write failed.");
+		let cache = Cache::load(dir.join("manifest"));
+		cache.update(&src, &Output::InPlace, gzipped, gzip, Some(&minified));
+		cache.save().expect("Cache save failed.");
+		let cache = Cache::load(dir.join("manifest"));
+		assert!(cache.check(&src, &Output::InPlace, gzipped).is_some());
+		std::fs::remove_file(&gz).expect("Remove failed.");
+		assert!(cache.check(&src, &Output::InPlace, gzipped).is_none());
+
+		let _res = std::fs::remove_dir_all(&dir);
+	}
+}
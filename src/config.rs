@@ -0,0 +1,491 @@
+/*!
+# HTMinL: Configuration Files.
+*/
+
+use crate::{
+	HtminlError,
+	MinifyOptions,
+	passes::ConfigPass,
+	Precompress,
+	Preserve,
+};
+use dowser::Extension;
+use fyi_msg::Msg;
+use globset::GlobSet;
+use serde::Deserialize;
+use std::{
+	collections::HashMap,
+	path::{
+		Path,
+		PathBuf,
+	},
+	sync::{
+		Arc,
+		Mutex,
+	},
+};
+
+
+
+/// # Config File Name.
+pub(super) const FILE_NAME: &str = "htminl.toml";
+
+
+
+#[derive(Debug)]
+/// # Configuration Files.
+///
+/// This holds the base `htminl.toml` — the one in the working directory —
+/// along with any others found in the crawled directories, which override
+/// their parents' settings for their own subtrees.
+///
+/// Nested files are loaded lazily and cached, so this can be shared across
+/// threads.
+pub(super) struct Configs {
+	/// # Base Directory.
+	base: PathBuf,
+
+	/// # Source Roots.
+	///
+	/// Nested files are only searched for between documents and their
+	/// (nearest) roots.
+	roots: Vec<PathBuf>,
+
+	/// # Parsed Files (By Directory).
+	files: Mutex<HashMap<PathBuf, Option<Arc<ConfigFile>>>>,
+}
+
+impl Configs {
+	/// # Load.
+	///
+	/// Load the base `htminl.toml` from the working directory, if any.
+	///
+	/// ## Errors
+	///
+	/// This will return an error if the base file exists but cannot be parsed.
+	pub(super) fn load(roots: Vec<PathBuf>) -> Result<Self, HtminlError> {
+		let base = std::env::current_dir()
+			.and_then(std::fs::canonicalize)
+			.unwrap_or_default();
+		let file = ConfigFile::load(&base)?.map(Arc::new);
+
+		let mut files = HashMap::new();
+		files.insert(base.clone(), file);
+
+		Ok(Self { base, roots, files: Mutex::new(files) })
+	}
+
+	#[must_use]
+	/// # Resolve.
+	///
+	/// Return the applicable settings for the document at `src`, or the
+	/// base settings if `None` (e.g. STDIN).
+	pub(super) fn resolve(&self, src: Option<&Path>) -> Resolved {
+		let mut chain = Vec::new();
+		let mut has_base = false;
+		if let Some(src) = src {
+			for dir in src.ancestors().skip(1) {
+				if let Some(file) = self.file(dir) { chain.push(file); }
+				if dir == self.base {
+					has_base = true;
+					break;
+				}
+				if self.roots.iter().any(|r| r == dir) { break; }
+			}
+		}
+
+		// Add the base if we didn't pass through it.
+		if ! has_base && let Some(file) = self.file(&self.base) { chain.push(file); }
+
+		// Shallowest first.
+		chain.reverse();
+		Resolved(chain)
+	}
+
+	/// # File.
+	///
+	/// Return the (cached) configuration for `dir`, if any, loading it for
+	/// the first time if needed.
+	///
+	/// Since nested files are loaded on-the-fly, parse errors are printed as
+	/// warnings and the offending file is ignored.
+	fn file(&self, dir: &Path) -> Option<Arc<ConfigFile>> {
+		let mut files = self.files.lock().ok()?;
+		files.entry(dir.to_path_buf())
+			.or_insert_with(|| match ConfigFile::load(dir) {
+				Ok(file) => file.map(Arc::new),
+				Err(e) => {
+					crate::eprint(Msg::warning(e.to_string()));
+					None
+				},
+			})
+			.clone()
+	}
+}
+
+
+
+#[derive(Debug, Default)]
+/// # Resolved Settings.
+///
+/// The configuration files applicable to a given document, shallowest first.
+/// For each setting, the deepest file with an opinion wins.
+pub(super) struct Resolved(Vec<Arc<ConfigFile>>);
+
+impl Resolved {
+	#[must_use]
+	/// # Extensions.
+	pub(super) fn exts(&self) -> &[Extension] {
+		self.0.iter().rev().find_map(|f| f.exts.as_deref()).unwrap_or_default()
+	}
+
+	#[must_use]
+	/// # Matches Globs?
+	///
+	/// Patterns are tested against the file name and the path relative to
+	/// the directory of the configuration file that defined them. Includes,
+	/// if any, must match; excludes must not.
+	pub(super) fn matches_globs(&self, src: &Path) -> bool {
+		let include = self.0.iter().rev().find_map(|f| f.include.as_ref());
+		let exclude = self.0.iter().rev().find_map(|f| f.exclude.as_ref());
+
+		include.is_none_or(|g| g.set.is_empty() || g.is_match(src)) &&
+		! exclude.is_some_and(|g| g.is_match(src))
+	}
+
+	#[must_use]
+	/// # Fingerprint.
+	///
+	/// Hash the resolved settings affecting minification — `opts`, as
+	/// returned by `Resolved::options` — along with the output-affecting CLI
+	/// flags and HTMinL version, so cached results can be invalidated when
+	/// any of them change.
+	pub(super) fn fingerprint(
+		&self,
+		opts: &MinifyOptions,
+		precompress: Option<Precompress>,
+		preserve: Preserve,
+	) -> u64 {
+		// The options only know the names of the passes, so include their
+		// configurations separately.
+		let passes = self.0.iter().rev().find_map(|f| f.passes.as_ref());
+		let raw = format!(
+			"{}\n{opts:?}\n{passes:?}\n{precompress:?}\n{preserve:?}",
+			env!("CARGO_PKG_VERSION"),
+		);
+		xxhash_rust::xxh3::xxh3_64(raw.as_bytes())
+	}
+
+	#[must_use]
+	/// # Minification Options.
+	pub(super) fn options(&self) -> MinifyOptions {
+		let mut out = self.0.iter().rev()
+			.find_map(|f| f.preset.clone())
+			.unwrap_or_default();
+		if let Some(v) = self.0.iter().rev().find_map(|f| f.comments) {
+			out = out.with_comments(v);
+		}
+		if let Some(v) = self.0.iter().rev().find_map(|f| f.collapse.as_ref()) {
+			out = out.with_collapse(v);
+		}
+		if let Some(v) = self.0.iter().rev().find_map(|f| f.preserve.as_ref()) {
+			out = out.with_preserve(v);
+		}
+		if let Some(v) = self.0.iter().rev().find_map(|f| f.passes.as_ref()) {
+			for pass in v { out = out.with_pass(pass.clone()); }
+		}
+		out
+	}
+}
+
+
+
+#[derive(Debug)]
+/// # Configuration File.
+///
+/// A parsed `htminl.toml`. Everything is optional; `None` defers to the
+/// parent.
+struct ConfigFile {
+	/// # Extensions.
+	exts: Option<Vec<Extension>>,
+
+	/// # Include Globs.
+	include: Option<Globs>,
+
+	/// # Exclude Globs.
+	exclude: Option<Globs>,
+
+	/// # Preset.
+	///
+	/// The base options, before any of the other settings are applied.
+	preset: Option<MinifyOptions>,
+
+	/// # Preserve Comments?
+	comments: Option<bool>,
+
+	/// # Collapsible Custom Elements.
+	collapse: Option<Vec<String>>,
+
+	/// # Preserved Elements.
+	preserve: Option<Vec<String>>,
+
+	/// # Custom Passes.
+	passes: Option<Vec<ConfigPass>>,
+}
+
+impl ConfigFile {
+	/// # Load.
+	///
+	/// Read and parse the `htminl.toml` in `dir`, if any.
+	///
+	/// ## Errors
+	///
+	/// This will return an error if the file exists but cannot be read or
+	/// parsed.
+	fn load(dir: &Path) -> Result<Option<Self>, HtminlError> {
+		let src = dir.join(FILE_NAME);
+		if ! src.is_file() { return Ok(None); }
+
+		std::fs::read_to_string(&src)
+			.map_err(|e| e.to_string())
+			.and_then(|raw| Self::parse(dir, &raw))
+			.map(Some)
+			.map_err(|e| HtminlError::Config(format!("{}: {e}", src.display())))
+	}
+
+	/// # Parse.
+	///
+	/// Parse the raw TOML contents of a file living in `dir`.
+	///
+	/// ## Errors
+	///
+	/// This will return an error — as a string — if the TOML is malformed
+	/// or any of the values are invalid.
+	fn parse(dir: &Path, raw: &str) -> Result<Self, String> {
+		let raw: RawConfig = toml::from_str(raw).map_err(|e| e.message().to_owned())?;
+
+		let exts = match raw.ext {
+			Some(exts) => Some(
+				crate::discover::parse_exts(&exts).ok_or("invalid ext")?
+			),
+			None => None,
+		};
+		let include = raw.include.map(|v| Globs::new(dir, &v)).transpose()?;
+		let exclude = raw.exclude.map(|v| Globs::new(dir, &v)).transpose()?;
+		let preset = match raw.preset.as_deref() {
+			Some("safe") => Some(MinifyOptions::safe()),
+			Some("default") => Some(MinifyOptions::default()),
+			Some("aggressive") => Some(MinifyOptions::aggressive()),
+			Some(_) => return Err("invalid preset".to_owned()),
+			None => None,
+		};
+		if let Some(passes) = raw.pass.as_ref() {
+			for pass in passes { pass.validate()?; }
+		}
+		let (collapse, preserve) = raw.whitespace.map_or((None, None), |ws| (
+			ws.collapse,
+			ws.preserve,
+		));
+
+		Ok(Self {
+			exts,
+			include,
+			exclude,
+			preset,
+			comments: raw.preserve_comments,
+			collapse,
+			preserve,
+			passes: raw.pass,
+		})
+	}
+}
+
+
+
+#[derive(Debug)]
+/// # Relative Globs.
+struct Globs {
+	/// # Base Directory.
+	dir: PathBuf,
+
+	/// # Patterns.
+	set: GlobSet,
+}
+
+impl Globs {
+	/// # New.
+	///
+	/// ## Errors
+	///
+	/// This will return an error — as a string — if any of the patterns are
+	/// invalid.
+	fn new(dir: &Path, globs: &[String]) -> Result<Self, String> {
+		let set = crate::discover::glob_set(globs)
+			.map_err(|e| e.to_string())?
+			.unwrap_or_else(GlobSet::empty);
+		Ok(Self { dir: dir.to_path_buf(), set })
+	}
+
+	#[must_use]
+	/// # Is Match?
+	fn is_match(&self, src: &Path) -> bool {
+		src.strip_prefix(&self.dir).is_ok_and(|p| self.set.is_match(p)) ||
+		src.file_name().is_some_and(|p| self.set.is_match(Path::new(p)))
+	}
+}
+
+
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+/// # Raw Configuration.
+struct RawConfig {
+	/// # Extensions.
+	ext: Option<Vec<String>>,
+
+	/// # Include Globs.
+	include: Option<Vec<String>>,
+
+	/// # Exclude Globs.
+	exclude: Option<Vec<String>>,
+
+	/// # Preset.
+	preset: Option<String>,
+
+	/// # Preserve Comments?
+	preserve_comments: Option<bool>,
+
+	/// # Whitespace Rules.
+	whitespace: Option<RawWhitespace>,
+
+	/// # Custom Passes.
+	pass: Option<Vec<ConfigPass>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+/// # Raw Whitespace Rules.
+struct RawWhitespace {
+	/// # Collapsible Custom Elements.
+	collapse: Option<Vec<String>>,
+
+	/// # Preserved Elements.
+	preserve: Option<Vec<String>>,
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn t_parse() {
+		let dir = Path::new("/tmp/site");
+		let file = ConfigFile::parse(dir, r#"
+ext = ["xhtml"]
+exclude = ["vendor/**"]
+preserve-comments = true
+
+[whitespace]
+collapse = ["My-Card", "my-card"]
+preserve = ["my-code"]
+
+[[pass]]
+kind = "strip-attrs"
+attrs = ["data-test"]
+
+[[pass]]
+kind = "rewrite-urls"
+from = "https://old.com/"
+to = "/"
+"#).expect("Config parse failed.");
+
+		assert_eq!(file.exts.as_ref().map(Vec::len), Some(1));
+		assert!(file.include.is_none());
+		assert_eq!(file.comments, Some(true));
+		assert_eq!(file.collapse.as_deref(), Some(&["My-Card".to_owned(), "my-card".to_owned()][..]));
+		assert_eq!(file.preserve.as_deref(), Some(&["my-code".to_owned()][..]));
+		assert_eq!(
+			file.passes.as_deref(),
+			Some(&[
+				ConfigPass::StripAttrs { attrs: vec!["data-test".to_owned()] },
+				ConfigPass::RewriteUrls { from: "https://old.com/".to_owned(), to: "/".to_owned() },
+			][..]),
+		);
+
+		let exclude = file.exclude.as_ref().expect("Missing excludes.");
+		assert!(exclude.is_match(Path::new("/tmp/site/vendor/a/b.html")));
+		assert!(! exclude.is_match(Path::new("/tmp/site/a/vendor/b.html")));
+
+		// Bad stuff.
+		assert!(ConfigFile::parse(dir, "nope = true").is_err());
+		assert!(ConfigFile::parse(dir, "exclude = [\"a[\"]").is_err());
+		assert!(ConfigFile::parse(dir, "preserve-comments = \"yes\"").is_err());
+		assert!(ConfigFile::parse(dir, "preset = \"extreme\"").is_err());
+		assert!(ConfigFile::parse(dir, "[[pass]]\nkind = \"nope\"").is_err());
+		assert!(ConfigFile::parse(dir, "[[pass]]\nkind = \"rewrite-urls\"\nfrom = \"\"\nto = \"/\"").is_err());
+	}
+
+	#[test]
+	fn t_resolve() {
+		let parent = ConfigFile::parse(Path::new("/tmp/site"), r#"
+exclude = ["*.tpl.html"]
+preserve-comments = true
+
+[whitespace]
+collapse = ["my-card"]
+"#).expect("Config parse failed.");
+		let child = ConfigFile::parse(Path::new("/tmp/site/blog"), "
+exclude = []
+preset = \"aggressive\"
+preserve-comments = false
+").expect("Config parse failed.");
+
+		// The parent alone.
+		let res = Resolved(vec![Arc::new(parent)]);
+		assert_eq!(
+			res.options(),
+			MinifyOptions::default().with_comments(true).with_collapse(["my-card"]),
+		);
+		assert!(! res.matches_globs(Path::new("/tmp/site/blog/index.tpl.html")));
+		assert!(res.matches_globs(Path::new("/tmp/site/blog/index.html")));
+
+		// With the child overriding it.
+		let res = Resolved(vec![Arc::clone(&res.0[0]), Arc::new(child)]);
+		assert_eq!(
+			res.options(),
+			MinifyOptions::aggressive().with_collapse(["my-card"]),
+		);
+		assert!(res.matches_globs(Path::new("/tmp/site/blog/index.tpl.html")));
+	}
+
+	#[test]
+	fn t_fingerprint() {
+		let fingerprint = |raw: &str| {
+			let res = Resolved(vec![Arc::new(
+				ConfigFile::parse(Path::new("/tmp/site"), raw).expect("Config parse failed.")
+			)]);
+			res.fingerprint(&res.options(), None, Preserve::default())
+		};
+
+		let base = Resolved::default();
+		let base = base.fingerprint(&base.options(), None, Preserve::default());
+		assert_eq!(base, fingerprint(""), "Empty configs should match no config.");
+		assert_eq!(base, fingerprint("exclude = [\"vendor/**\"]"), "Globs don't affect minification.");
+
+		// Anything affecting minification should change it.
+		let a = fingerprint("preserve-comments = true");
+		let b = fingerprint("[[pass]]\nkind = \"strip-attrs\"\nattrs = [\"data-test\"]");
+		let c = fingerprint("[[pass]]\nkind = \"strip-attrs\"\nattrs = [\"data-qa\"]");
+		assert_ne!(base, a);
+		assert_ne!(base, b);
+		assert_ne!(b, c);
+
+		// As should output-affecting CLI flags.
+		let res = Resolved::default();
+		let opts = res.options();
+		assert_ne!(base, res.fingerprint(&opts, Precompress::from_cli("gzip"), Preserve::default()));
+		assert_ne!(base, res.fingerprint(&opts, None, Preserve::new(true, false)));
+	}
+}
@@ -0,0 +1,131 @@
+/*!
+# HTMinL: Diffs.
+*/
+
+use similar::TextDiff;
+use std::{
+	borrow::Cow,
+	io::Write,
+	path::Path,
+};
+
+
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+/// # Diff Style.
+///
+/// This is used for previewing changes without saving them.
+pub(super) enum Diff {
+	/// # Line-by-Line.
+	Plain,
+
+	/// # Line-by-Line, Tag-by-Tag.
+	///
+	/// Minified documents often consist of a single (very long) line, so
+	/// this style breaks the before and after versions at each tag before
+	/// diffing to make the changes easier to follow.
+	Pretty,
+}
+
+impl Diff {
+	#[must_use]
+	/// # Unified Diff.
+	///
+	/// Return a unified diff of the `before` and `after` versions of `src`.
+	pub(super) fn unified(self, src: &Path, before: &str, after: &str) -> String {
+		let (before, after) = match self {
+			Self::Plain => (Cow::Borrowed(before), Cow::Borrowed(after)),
+			Self::Pretty => (Cow::Owned(pretty(before)), Cow::Owned(pretty(after))),
+		};
+
+		let src = src.to_string_lossy();
+		TextDiff::from_lines(before.as_ref(), after.as_ref())
+			.unified_diff()
+			.context_radius(3)
+			.header(&src, &format!("{src} (minified)"))
+			.to_string()
+	}
+
+	/// # Print Unified Diff.
+	///
+	/// Print a unified diff of the `before` and `after` versions of `src` to
+	/// STDOUT in one go.
+	pub(super) fn print(self, src: &Path, before: &str, after: &str) {
+		let diff = self.unified(src, before, after);
+		let mut stdout = std::io::stdout().lock();
+		let _res = stdout.write_all(diff.as_bytes()).and_then(|()| stdout.flush());
+	}
+}
+
+
+
+#[must_use]
+/// # (Lightly) Pretty Print.
+///
+/// Add a line break before every `<` that isn't already at the start of a
+/// line (ignoring indentation). The result isn't pretty in any absolute
+/// sense, but is much more diffable.
+fn pretty(src: &str) -> String {
+	let mut out = String::with_capacity(src.len() + src.len() / 8);
+	let mut blank = true; // Nothing but whitespace since the last break.
+	for c in src.chars() {
+		if c == '<' && ! blank { out.push('\n'); }
+		out.push(c);
+		if c == '\n' { blank = true; }
+		else if ! c.is_whitespace() { blank = false; }
+	}
+	out
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn t_pretty() {
+		assert_eq!(
+			pretty("<div>\n  <p>Hello</p><p>World</p></div>"),
+			"<div>\n  <p>Hello\n</p>\n<p>World\n</p>\n</div>",
+		);
+	}
+
+	#[test]
+	fn t_unified() {
+		let src = Path::new("index.html");
+
+		assert_eq!(
+			Diff::Plain.unified(
+				src,
+				"<div>\n  <p>Hello   World</p>\n</div>\n",
+				"<div> <p>Hello World</p> </div>\n",
+			),
+			"--- index.html
++++ index.html (minified)
+@@ -1,3 +1 @@
+-<div>
+-  <p>Hello   World</p>
+-</div>
++<div> <p>Hello World</p> </div>
+",
+		);
+
+		assert_eq!(
+			Diff::Pretty.unified(
+				src,
+				"<p>Hello</p><p>World</p>\n",
+				"<p>Hello</p><p>Moon</p>\n",
+			),
+			"--- index.html
++++ index.html (minified)
+@@ -1,4 +1,4 @@
+ <p>Hello
+ </p>
+-<p>World
++<p>Moon
+ </p>
+",
+		);
+	}
+}
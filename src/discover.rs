@@ -0,0 +1,414 @@
+/*!
+# HTMinL: Discovery.
+*/
+
+use crate::{
+	Configs,
+	E_HTM,
+	E_HTML,
+};
+use dowser::Extension;
+use globset::{
+	GlobBuilder,
+	GlobSet,
+	GlobSetBuilder,
+};
+use ignore::{
+	gitignore::{
+		Gitignore,
+		GitignoreBuilder,
+	},
+	Match,
+};
+use std::{
+	collections::HashMap,
+	fs::File,
+	io::Read,
+	path::{
+		Path,
+		PathBuf,
+	},
+	sync::Arc,
+};
+
+
+
+/// # Sniff Length.
+///
+/// The maximum number of bytes to read from unknown files when sniffing.
+const SNIFF_LEN: u64 = 1024;
+
+/// # Ignore Files.
+///
+/// These are checked — in order of increasing precedence — when honoring
+/// ignore files.
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+
+
+#[derive(Debug, Clone)]
+/// # Discovery Rules.
+///
+/// This decides which of the crawled files are actually HTML documents worth
+/// minifying.
+pub(super) struct Discovery {
+	/// # Source Roots.
+	///
+	/// Canonical source directories, shortest first, used for relative
+	/// glob-matching and to bound the ignore file search.
+	roots: Vec<PathBuf>,
+
+	/// # Extensions.
+	exts: Vec<Extension>,
+
+	/// # Sniff Unknown Files?
+	sniff: bool,
+
+	/// # Include Globs.
+	include: Option<GlobSet>,
+
+	/// # Exclude Globs.
+	exclude: Option<GlobSet>,
+
+	/// # Ignore Files (By Directory).
+	///
+	/// This is `None` unless ignore files are being honored, in which case
+	/// it caches the parsed rules — if any — for each directory visited.
+	ignores: Option<HashMap<PathBuf, Option<Gitignore>>>,
+
+	/// # Configuration Files.
+	configs: Option<Arc<Configs>>,
+}
+
+impl Discovery {
+	#[must_use]
+	/// # New.
+	///
+	/// Return the default rules — `.htm`/`.html` files, no sniffing, no
+	/// globs — for the given source roots.
+	pub(super) fn new(roots: Vec<PathBuf>) -> Self {
+		Self {
+			roots,
+			exts: vec![E_HTM, E_HTML],
+			sniff: false,
+			include: None,
+			exclude: None,
+			ignores: None,
+			configs: None,
+		}
+	}
+
+	/// # With Extensions.
+	///
+	/// Add the `--ext` values — which may be comma-separated and have leading
+	/// dots — to the extension list, replacing the default `.htm`/`.html` if
+	/// `keep_defaults` is false.
+	///
+	/// Returns `None` if any of the extensions are invalid.
+	pub(super) fn with_exts(mut self, exts: &[String], keep_defaults: bool) -> Option<Self> {
+		if ! keep_defaults { self.exts.clear(); }
+		self.exts.append(&mut parse_exts(exts)?);
+		self.exts.sort_unstable();
+		self.exts.dedup();
+
+		Some(self)
+	}
+
+	#[must_use]
+	/// # With Sniffing.
+	///
+	/// Files with unknown extensions will be sniffed for HTML if true.
+	pub(super) const fn with_sniff(mut self, sniff: bool) -> Self {
+		self.sniff = sniff;
+		self
+	}
+
+	/// # With Globs.
+	///
+	/// Set the `--include` and `--exclude` patterns.
+	///
+	/// Returns `None` if any of the patterns are invalid.
+	pub(super) fn with_globs(mut self, include: &[String], exclude: &[String]) -> Option<Self> {
+		self.include = glob_set(include).ok()?;
+		self.exclude = glob_set(exclude).ok()?;
+		Some(self)
+	}
+
+	#[must_use]
+	/// # With Ignore Files.
+	///
+	/// If true, `.gitignore` and `.ignore` files found in the crawled
+	/// directories will be honored.
+	pub(super) fn with_ignore_files(mut self, ignore: bool) -> Self {
+		self.ignores = if ignore { Some(HashMap::new()) } else { None };
+		self
+	}
+
+	#[must_use]
+	/// # With Configuration Files.
+	///
+	/// Apply the extension and glob rules from any `htminl.toml` files too.
+	pub(super) fn with_configs(mut self, configs: Option<Arc<Configs>>) -> Self {
+		self.configs = configs;
+		self
+	}
+
+	/// # Retain Matches.
+	///
+	/// Prune any `paths` that don't match the rules.
+	pub(super) fn retain(&mut self, paths: &mut Vec<PathBuf>) {
+		paths.retain(|p| self.matches(p));
+	}
+
+	/// # Matches?
+	///
+	/// Returns `true` if `src` has one of the extensions — or is sniffed out
+	/// as HTML — and isn't excluded by the globs, ignore files, or
+	/// configuration files.
+	///
+	/// The checks run roughly from cheapest to most expensive.
+	pub(super) fn matches(&mut self, src: &Path) -> bool {
+		let config = self.configs.as_ref().map(|c| c.resolve(Some(src)));
+		let known = Extension::from_path(src).is_some_and(|e|
+			self.exts.contains(&e) ||
+			config.as_ref().is_some_and(|c| c.exts().contains(&e))
+		);
+		if ! known && ! self.sniff { return false; }
+
+		let root = self.roots.iter().find(|r| src.starts_with(r)).map(PathBuf::as_path);
+		if ! self.matches_globs(src, root) { return false; }
+		if config.is_some_and(|c| ! c.matches_globs(src)) { return false; }
+		if
+			let Some(root) = root &&
+			let Some(ignores) = self.ignores.as_mut() &&
+			is_ignored(ignores, src, root)
+		{ return false; }
+
+		known || sniff(src)
+	}
+
+	/// # Matches Globs?
+	///
+	/// Patterns are tested against the file name, the path relative to its
+	/// root, and the full path. Includes, if any, must match; excludes must
+	/// not.
+	fn matches_globs(&self, src: &Path, root: Option<&Path>) -> bool {
+		if self.include.is_none() && self.exclude.is_none() { return true; }
+
+		let rel = root.and_then(|r| src.strip_prefix(r).ok());
+		let name = src.file_name().map(Path::new);
+		let is_match = |set: &GlobSet|
+			set.is_match(src) ||
+			rel.is_some_and(|p| set.is_match(p)) ||
+			name.is_some_and(|p| set.is_match(p));
+
+		self.include.as_ref().is_none_or(is_match) &&
+		! self.exclude.as_ref().is_some_and(is_match)
+	}
+}
+
+
+
+#[must_use]
+/// # Source Roots.
+///
+/// Canonicalize the `entries` — the original file and/or directory paths
+/// passed to the program — into root directories, sorted shortest first.
+///
+/// Files are relative to their parent directories; directories to
+/// themselves.
+pub(super) fn roots(entries: &[PathBuf]) -> Vec<PathBuf> {
+	let mut roots: Vec<PathBuf> = entries.iter()
+		.filter_map(|p| {
+			let p = std::fs::canonicalize(p).ok()?;
+			if p.is_dir() { Some(p) }
+			else { p.parent().map(Path::to_path_buf) }
+		})
+		.collect();
+	roots.sort_by_key(|p| p.as_os_str().len());
+	roots.dedup();
+	roots
+}
+
+/// # Parse Extensions.
+///
+/// Parse extensions — which may be comma-separated and have leading dots —
+/// returning `None` if any are invalid.
+pub(super) fn parse_exts(exts: &[String]) -> Option<Vec<Extension>> {
+	exts.iter()
+		.flat_map(|e| e.split(','))
+		.map(|ext| {
+			let ext = ext.trim();
+			Extension::new(ext.strip_prefix('.').unwrap_or(ext))
+		})
+		.collect()
+}
+
+/// # Build Glob Set.
+///
+/// Returns `None` if there are no patterns.
+///
+/// ## Errors
+///
+/// This will return an error if any of the patterns are invalid.
+pub(super) fn glob_set(globs: &[String]) -> Result<Option<GlobSet>, globset::Error> {
+	if globs.is_empty() { return Ok(None); }
+
+	let mut set = GlobSetBuilder::new();
+	for glob in globs {
+		set.add(GlobBuilder::new(glob.trim()).literal_separator(true).build()?);
+	}
+	set.build().map(Some)
+}
+
+/// # Is Ignored?
+///
+/// Check the ignore files between `src` and `root`, if any, deepest first,
+/// caching the parsed rules in `ignores` as we go. The first one with an
+/// opinion wins.
+fn is_ignored(
+	ignores: &mut HashMap<PathBuf, Option<Gitignore>>,
+	src: &Path,
+	root: &Path,
+) -> bool {
+	for dir in src.ancestors().skip(1) {
+		let rules = ignores.entry(dir.to_path_buf()).or_insert_with(|| ignore_rules(dir));
+		match rules.as_ref().map(|r| r.matched_path_or_any_parents(src, false)) {
+			Some(Match::Ignore(_)) => return true,
+			Some(Match::Whitelist(_)) => return false,
+			_ => {},
+		}
+		if dir == root { break; }
+	}
+	false
+}
+
+/// # Ignore Rules.
+///
+/// Parse the ignore file(s) in `dir`, if any.
+fn ignore_rules(dir: &Path) -> Option<Gitignore> {
+	let mut builder = GitignoreBuilder::new(dir);
+	for file in IGNORE_FILES {
+		let file = dir.join(file);
+		if file.is_file() { let _res = builder.add(file); }
+	}
+	builder.build().ok().filter(|r| ! r.is_empty())
+}
+
+/// # Sniff.
+///
+/// Returns `true` if the file at `src` looks like HTML on the inside.
+fn sniff(src: &Path) -> bool {
+	let mut buf = Vec::new();
+	File::open(src).and_then(|f| f.take(SNIFF_LEN).read_to_end(&mut buf))
+		.is_ok_and(|_| is_html(&buf))
+}
+
+#[must_use]
+/// # Is HTML?
+///
+/// Returns `true` if `src` — ignoring any leading BOM, whitespace, and/or
+/// comments — opens with an HTML doctype or `<html>` root tag.
+fn is_html(mut src: &[u8]) -> bool {
+	/// # Starts With (Case-Insensitive)?
+	fn starts_with(src: &[u8], prefix: &[u8]) -> bool {
+		src.len() >= prefix.len() && src[..prefix.len()].eq_ignore_ascii_case(prefix)
+	}
+
+	src = src.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(src);
+	loop {
+		src = src.trim_ascii_start();
+		if let Some(rest) = src.strip_prefix(b"<!--") {
+			// Skip to the end of the comment, if it ends.
+			let Some(end) = rest.windows(3).position(|w| w == b"-->") else { return false; };
+			src = &rest[end + 3..];
+		}
+		else { break; }
+	}
+
+	let rest =
+		if starts_with(src, b"<!doctype") { src[9..].trim_ascii_start() }
+		else if let Some(rest) = src.strip_prefix(b"<") { rest }
+		else { return false; };
+
+	starts_with(rest, b"html") &&
+	rest.get(4).is_none_or(|b| b.is_ascii_whitespace() || *b == b'>')
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn t_exts() {
+		let mut disc = Discovery::new(vec![PathBuf::from("/foo")])
+			.with_exts(&[".shtml,xhtml".to_owned(), "HTML".to_owned()], true)
+			.expect("Discovery failed.");
+		assert_eq!(disc.exts.len(), 4);
+		assert!(disc.matches(Path::new("/foo/index.shtml")));
+		assert!(disc.matches(Path::new("/foo/index.tpl.html")));
+		assert!(! disc.matches(Path::new("/foo/index")));
+
+		let mut disc = Discovery::new(vec![PathBuf::from("/foo")])
+			.with_exts(&["xhtml".to_owned()], false)
+			.expect("Discovery failed.");
+		assert_eq!(disc.exts, [Extension::new("xhtml").unwrap()]);
+		assert!(! disc.matches(Path::new("/foo/index.html")));
+
+		let disc = Discovery::new(Vec::new());
+		assert!(disc.clone().with_exts(&["a.b".to_owned()], true).is_none());
+		assert!(disc.with_exts(&[String::new()], true).is_none());
+	}
+
+	#[test]
+	fn t_globs() {
+		let mut disc = Discovery::new(vec![PathBuf::from("/site")])
+			.with_globs(
+				&["blog/**".to_owned(), "*.tpl.html".to_owned()],
+				&["**/vendor/**".to_owned(), "/site/blog/draft.html".to_owned()],
+			)
+			.expect("Discovery failed.");
+
+		for (p, expected) in [
+			("/site/blog/index.html", true),
+			("/site/blog/2020/index.html", true),
+			("/site/page.tpl.html", true),
+			("/site/emails/page.tpl.html", true),
+			("/site/index.html", false),
+			("/site/blog/vendor/index.html", false),
+			("/site/blog/draft.html", false),
+		] {
+			assert_eq!(disc.matches(Path::new(p)), expected, "{p}");
+		}
+
+		assert!(Discovery::new(Vec::new()).with_globs(&["a[".to_owned()], &[]).is_none());
+	}
+
+	#[test]
+	fn t_is_html() {
+		for i in [
+			"<!DOCTYPE html>",
+			"<!doctype html><html>",
+			"\u{feff}  <!-- Comment. --> <!doctype HTML>",
+			"<html lang=\"en\">",
+			"<HTML>",
+			"<html",
+		] {
+			assert!(is_html(i.as_bytes()), "{i:?}");
+		}
+
+		for i in [
+			"",
+			"Hello World",
+			"<!-- Unclosed <!doctype html>",
+			"<!doctype xml>",
+			"<htmlx>",
+			"<div>",
+			"{\"html\": true}",
+		] {
+			assert!(! is_html(i.as_bytes()), "{i:?}");
+		}
+	}
+}
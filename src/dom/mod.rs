@@ -3,10 +3,16 @@
 */
 
 pub(super) mod node;
+pub(super) mod options;
+pub(super) mod pass;
+pub(super) mod savings;
+mod stream;
+pub(super) mod timings;
 
 use crate::{
 	Handle,
-	HtminlError,
+	MinifyError,
+	MinifyOptions,
 	Node,
 	NodeInner,
 };
@@ -31,11 +37,32 @@ use html5ever::{
 	tree_builder::TreeBuilderOpts,
 };
 use indexmap::IndexMap;
+use pass::{
+	Element,
+	Pass,
+	Stage,
+	Text,
+};
+use savings::{
+	Category,
+	Savings,
+};
 use std::{
 	borrow::Cow,
-	io::Cursor,
+	io::{
+		self,
+		Cursor,
+	},
 	rc::Rc,
-	cell::RefCell,
+	cell::{
+		Cell,
+		RefCell,
+	},
+	time::Instant,
+};
+use timings::{
+	Phase,
+	Timings,
 };
 
 
@@ -46,6 +73,11 @@ use std::{
 /// methods.
 static NOOP_NAME: ExpandedName = expanded_name!("", "abbr");
 
+/// # Doctype.
+///
+/// This is the only doctype we write.
+const DOCTYPE: &str = "<!DOCTYPE html>";
+
 
 
 #[derive(Debug, Clone)]
@@ -59,7 +91,16 @@ pub(crate) struct Tree {
 	root: Handle,
 
 	/// # Error.
-	error: RefCell<Option<HtminlError>>,
+	error: RefCell<Option<MinifyError>>,
+
+	/// # Options.
+	opts: MinifyOptions,
+
+	/// # Savings Breakdown.
+	savings: Cell<Savings>,
+
+	/// # Phase Timings.
+	timings: Cell<Timings>,
 }
 
 impl Default for Tree {
@@ -69,6 +110,9 @@ impl Default for Tree {
 		Self {
 			root: Node::new(NodeInner::Document),
 			error: RefCell::new(None),
+			opts: MinifyOptions::default(),
+			savings: Cell::new(Savings::default()),
+			timings: Cell::new(Timings::default()),
 		}
 	}
 }
@@ -99,7 +143,8 @@ impl TreeSink for Tree {
 
 	/// # Append Node.
 	///
-	/// Attach a text or element child node to an existing (parent) node.
+	/// Attach a text, element, or (preserved) comment child node to an
+	/// existing (parent) node.
 	///
 	/// Other node types are ignored.
 	fn append(&self, parent: &Handle, child: NodeOrText<Handle>) {
@@ -121,8 +166,11 @@ impl TreeSink for Tree {
 				},
 
 			// Among the other possible node types, we're only actually
-			// interested in elements.
-			NodeOrText::AppendNode(v) => if matches!(v.inner, NodeInner::Element { .. }) {
+			// interested in elements and comments.
+			NodeOrText::AppendNode(v) => if matches!(
+				v.inner,
+				NodeInner::Element { .. } | NodeInner::Comment { .. }
+			) {
 				parent.children.borrow_mut().push(v);
 			},
 		}
@@ -151,14 +199,14 @@ impl TreeSink for Tree {
 	/// Note: this isn't usually used.
 	fn append_before_sibling(&self, sibling: &Handle, child: NodeOrText<Handle>) {
 		let Some((parent, pos)) = self.find_node_parent_and_index(sibling) else {
-			self.error.borrow_mut().replace(HtminlError::Parse);
+			self.fail("append_before_sibling: the sibling has no parent.");
 			return;
 		};
 
 		// Unwrap the children.
 		let children: &mut Vec<_> = &mut parent.children.borrow_mut();
 		if children.len() <= pos {
-			self.error.borrow_mut().replace(HtminlError::Parse);
+			self.fail("append_before_sibling: the sibling is out of range.");
 			return;
 		}
 
@@ -180,8 +228,11 @@ impl TreeSink for Tree {
 				},
 
 			// Among the other possible node types, we're only actually
-			// interested in elements.
-			NodeOrText::AppendNode(v) => if matches!(v.inner, NodeInner::Element { .. }) {
+			// interested in elements and comments.
+			NodeOrText::AppendNode(v) => if matches!(
+				v.inner,
+				NodeInner::Element { .. } | NodeInner::Comment { .. }
+			) {
 				children.insert(pos - 1, v);
 			},
 		}
@@ -189,9 +240,17 @@ impl TreeSink for Tree {
 
 	/// # Create Comment.
 	///
-	/// Return a generic placeholder node that will be ignored if appended.
-	fn create_comment(&self, _text: StrTendril) -> Handle {
-		Node::new(NodeInner::Ignored)
+	/// Return a comment node if comments are being preserved, otherwise a
+	/// generic placeholder node that will be ignored if appended.
+	fn create_comment(&self, text: StrTendril) -> Handle {
+		if self.opts.comments {
+			Node::new(NodeInner::Comment { contents: text })
+		}
+		else {
+			// The contents plus the <!-- --> wrapper.
+			Savings::add(&self.savings, Category::Comments, text.len() + 7);
+			Node::new(NodeInner::Ignored)
+		}
 	}
 
 	/// # Create Element.
@@ -232,7 +291,7 @@ impl TreeSink for Tree {
 		}
 		else {
 			debug_assert!(false, "BUG: elem_name called on non-element node.");
-			self.error.borrow_mut().replace(HtminlError::Parse);
+			self.fail("elem_name: the target is not an element.");
 			NOOP_NAME
 		}
 	}
@@ -264,8 +323,8 @@ impl TreeSink for Tree {
 			return Rc::clone(out);
 		}
 
-		debug_assert!(false, "BUG: elem_name called on non-element node.");
-		self.error.borrow_mut().replace(HtminlError::Parse);
+		debug_assert!(false, "BUG: get_template_contents called on non-template node.");
+		self.fail("get_template_contents: the target is not a template.");
 		Node::new(NodeInner::Ignored)
 	}
 
@@ -326,10 +385,11 @@ impl Tree {
 	///
 	/// Parse RAW HTML (as bytes) into a proper (minified) tree, returning it
 	/// unless there's a show-stopping error of some kind.
-	pub(crate) fn parse(raw: &[u8]) -> Result<Self, HtminlError> {
+	pub(crate) fn parse(raw: &[u8], opts: &MinifyOptions)
+	-> Result<Self, MinifyError> {
 		// Since we aren't expecting anything other than HTML, we can skip the
 		// doctype and save a tiny bit of overhead.
-		let opts = ParseOpts {
+		let parse_opts = ParseOpts {
 			tree_builder: TreeBuilderOpts {
 				drop_doctype: true,
 				..TreeBuilderOpts::default()
@@ -338,17 +398,39 @@ impl Tree {
 		};
 
 		// Try to parse with our parser.
-		let dom = html5ever::parse_document(Self::default(), opts)
+		let now = Instant::now();
+		let tree = Self { opts: opts.clone(), ..Self::default() };
+
+		// The doctype is always rewritten as the short HTML5 version.
+		Savings::add(
+			&tree.savings,
+			Category::Doctype,
+			savings::doctype_len(raw).saturating_sub(DOCTYPE.len()),
+		);
+
+		let dom = html5ever::parse_document(tree, parse_opts)
 			.from_utf8()
 			.read_from(&mut Cursor::new(raw))
-			.map_err(|_| HtminlError::Parse)?;
+			.map_err(|_| MinifyError::Parse(
+				"read_from: the document could not be fed to the parser."
+			))?;
 
 		if let Some(e) = dom.error.borrow_mut().take() {
 			return Err(e);
 		}
+		Timings::add_cell(&dom.timings, Phase::Parse, now);
 
+		// Custom passes are timed along with the built-in pass they follow.
+		let now = Instant::now();
 		dom.post_process();
+		dom.run_passes(Stage::BeforeMinify);
+		Timings::add_cell(&dom.timings, Phase::PostProcess, now);
+
+		let now = Instant::now();
 		dom.minify();
+		dom.run_passes(Stage::AfterMinify);
+		Timings::add_cell(&dom.timings, Phase::Minify, now);
+
 		Ok(dom)
 	}
 
@@ -357,14 +439,82 @@ impl Tree {
 	/// Convert the tree back into an HTML string, returning it unless there
 	/// are any show-stopping errors.
 	pub(crate) fn serialize(&self, size_hint: Option<usize>)
-	-> Result<String, HtminlError> {
+	-> Result<String, MinifyError> {
 		use std::fmt::Write;
 
+		let now = Instant::now();
 		let size_hint = size_hint.unwrap_or(256);
 		let mut out = String::with_capacity(size_hint);
-		write!(&mut out, "{}", node::NodeDisplay::new(&self.root, None))
-			.map_err(|_| HtminlError::Save)
-			.map(|()| out)
+		write!(&mut out, "{}", node::NodeDisplay::new(&self.root, None, &self.opts, &self.savings))
+			.map_err(|_| MinifyError::Serialize)?;
+		Timings::add_cell(&self.timings, Phase::Serialize, now);
+		Ok(out)
+	}
+
+	/// # Serialize Document (Streaming).
+	///
+	/// Same as `Tree::serialize`, except the output is written — buffered —
+	/// straight into `writer` rather than collected into a `String`. The
+	/// total number of bytes written is returned on success.
+	///
+	/// If `fragment` is true, only the contents of the fragment marker
+	/// element are written, sparing the caller the trouble of fishing them
+	/// back out after the fact.
+	pub(crate) fn serialize_into<W: io::Write>(&self, writer: W, fragment: bool)
+	-> Result<u64, MinifyError> {
+		use std::fmt::Write;
+
+		let now = Instant::now();
+		let mut out = stream::IoFmt::new(writer);
+		let res =
+			if fragment {
+				let (marker, name) = find_fragment(&self.root).ok_or(MinifyError::Parse(
+					"The fragment markers went missing during minification."
+				))?;
+				marker.children.borrow().iter().try_for_each(|child| write!(
+					&mut out,
+					"{}",
+					node::NodeDisplay::new(child, Some(name.clone()), &self.opts, &self.savings),
+				))
+			}
+			else {
+				write!(
+					&mut out,
+					"{}",
+					node::NodeDisplay::new(&self.root, None, &self.opts, &self.savings),
+				)
+			};
+
+		// Blame the writer if it was the writer's fault.
+		if res.is_err() {
+			return Err(out.error().map_or(MinifyError::Serialize, MinifyError::Write));
+		}
+		let written = out.finish().map_err(MinifyError::Write)?;
+		Timings::add_cell(&self.timings, Phase::Serialize, now);
+		Ok(written)
+	}
+
+	#[must_use]
+	/// # Savings Breakdown.
+	///
+	/// Return the bytes saved by each category of optimization. Note that
+	/// the attribute-related savings are only tallied during serialization.
+	pub(crate) const fn savings(&self) -> Savings { self.savings.get() }
+
+	#[must_use]
+	/// # Phase Timings.
+	///
+	/// Return the time spent parsing, post-processing, minifying, and
+	/// serializing (if it has been) the tree.
+	pub(crate) const fn timings(&self) -> Timings { self.timings.get() }
+
+	/// # Fail.
+	///
+	/// Record a parse error from within one of the `TreeSink` callbacks,
+	/// noting which one and why. Only the first such error is kept, as
+	/// subsequent ones are likely just fallout.
+	fn fail(&self, message: &'static str) {
+		self.error.borrow_mut().get_or_insert(MinifyError::Parse(message));
 	}
 
 	#[must_use]
@@ -441,12 +591,46 @@ impl Tree {
 		walk(&self.root);
 	}
 
+	/// # Run Custom Passes.
+	///
+	/// Run each of the custom passes for `stage`, in order, over the entire
+	/// tree.
+	fn run_passes(&self, stage: Stage) {
+		/// # Visit Node by Node.
+		fn walk(handle: &Handle, parent: Option<&QualName>, pass: &dyn Pass) {
+			match handle.inner {
+				NodeInner::Element { ref name, ref attrs } => {
+					pass.element(&Element::new(name, attrs));
+					for child in handle.children.borrow().iter() {
+						walk(child, Some(name), pass);
+					}
+				},
+				NodeInner::Text { ref contents } => {
+					pass.text(&Text::new(parent, contents));
+				},
+				NodeInner::Document => {
+					for child in handle.children.borrow().iter() {
+						walk(child, None, pass);
+					}
+				},
+				NodeInner::Comment { .. } | NodeInner::Ignored => {},
+			}
+		}
+
+		for pass in self.opts.passes.stage(stage) { walk(&self.root, None, pass); }
+	}
+
 	/// # Minify Text Nodes.
 	fn minify(&self) {
 		/// # Minify Node by Node.
-		fn walk(handle: &Handle, ws: TextNormalization) {
+		fn walk(
+			handle: &Handle,
+			ws: TextNormalization,
+			opts: &MinifyOptions,
+			savings: &Cell<Savings>,
+		) {
 			// Maybe trim first/last text child.
-			let try_trim = match handle.inner {
+			let try_trim = opts.trim_whitespace && match handle.inner {
 				NodeInner::Document => true,
 				NodeInner::Element { ref name, .. } => can_trim_first_last_text(name),
 				_ => false,
@@ -459,6 +643,7 @@ impl Tree {
 						let contents: &mut StrTendril = &mut contents.borrow_mut();
 						let new: &str = contents.as_ref().trim_start();
 						if new != contents.as_ref() {
+							Savings::add(savings, Category::Whitespace, contents.len() - new.len());
 							*contents = StrTendril::from(new);
 						}
 					}
@@ -468,6 +653,7 @@ impl Tree {
 						let contents: &mut StrTendril = &mut contents.borrow_mut();
 						let new: &str = contents.as_ref().trim_end();
 						if new != contents.as_ref() {
+							Savings::add(savings, Category::Whitespace, contents.len() - new.len());
 							*contents = StrTendril::from(new);
 						}
 					}
@@ -479,13 +665,14 @@ impl Tree {
 				// Keep and/or replace the text if non-empty, otherwise drop it.
 				NodeInner::Text { ref contents } => {
 					let mut contents = contents.borrow_mut();
-					! contents.is_empty() && ws.normalize(contents.as_bytes()).is_none_or(|new|
+					! contents.is_empty() && ws.normalize(contents.as_bytes()).is_none_or(|new| {
+						Savings::add(savings, Category::Whitespace, contents.len() - new.len());
 						if new.is_empty() { false }
 						else {
 							*contents = new;
 							true
 						}
-					)
+					})
 				},
 
 				// Recurse the children of elements.
@@ -502,24 +689,30 @@ impl Tree {
 						return true;
 					}
 
+					// Nor with anything the user wants preserved.
+					if opts.is_preserved(name) { return true; }
+
 					// Recurse to strip their children.
-					walk(v, TextNormalization::new(name));
+					walk(v, TextNormalization::new(name, opts), opts, savings);
 					true
 				},
 
 				// This shouldn't be reachable, but if for some reason it hits, recurse
 				// same as if it were an element.
 				NodeInner::Document => {
-					walk(v, TextNormalization::Both);
+					walk(v, TextNormalization::document(opts), opts, savings);
 					true
 				},
 
+				// Comments only make it this far if they're wanted.
+				NodeInner::Comment { .. } => true,
+
 				// Ignored elements don't count.
 				NodeInner::Ignored => false,
 			});
 		}
 
-		walk(&self.root, TextNormalization::Both);
+		walk(&self.root, TextNormalization::document(&self.opts), &self.opts, &self.savings);
 	}
 }
 
@@ -544,11 +737,13 @@ enum TextNormalization {
 impl TextNormalization {
 	#[must_use]
 	/// # New.
-	const fn new(tag: &QualName) -> Self {
+	fn new(tag: &QualName, opts: &MinifyOptions) -> Self {
+		if ! opts.collapse_whitespace { return Self::None; }
+
 		match tag.ns {
 			// HTML is the main game, obviously.
 			ns!(html) => {
-				let collapse = can_collapse_whitespace(tag);
+				let collapse = can_collapse_whitespace(tag) || opts.is_collapsible(tag);
 				let drop = can_drop_whitespace_text(tag);
 				if collapse && drop { Self::Both }
 				else if collapse { Self::Collapse }
@@ -567,6 +762,16 @@ impl TextNormalization {
 		}
 	}
 
+	#[must_use]
+	/// # For Document.
+	///
+	/// The root gets the full treatment, unless whitespace collapsing has
+	/// been disabled altogether.
+	const fn document(opts: &MinifyOptions) -> Self {
+		if opts.collapse_whitespace { Self::Both }
+		else { Self::None }
+	}
+
 	#[must_use]
 	/// # Normalize Text.
 	///
@@ -834,6 +1039,25 @@ fn collapse(txt: &[u8]) -> Option<Vec<u8>> {
 	Some(new)
 }
 
+#[must_use]
+/// # Find Fragment Marker.
+///
+/// Return the (first) fragment marker element and its name, if any.
+fn find_fragment(handle: &Handle) -> Option<(Handle, QualName)> {
+	for child in handle.children.borrow().iter() {
+		if let NodeInner::Element { ref name, .. } = child.inner {
+			if
+				matches!(name.ns, ns!(html)) &&
+				name.local.as_ref() == crate::fragment::TAG
+			{
+				return Some((Rc::clone(child), name.clone()));
+			}
+			if let Some(found) = find_fragment(child) { return Some(found); }
+		}
+	}
+	None
+}
+
 #[must_use]
 /// # Is Void HTML Element?
 const fn is_void_html_tag(tag: &QualName) -> bool {
@@ -879,6 +1103,7 @@ const fn is_whitespace(mut txt: &[u8]) -> bool {
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use html5ever::LocalName;
 
 	/// # Predictable Test Tree.
 	const HTML: &[u8] = b"\
@@ -894,7 +1119,7 @@ mod tests {
 	#[test]
 	fn t_remove_from_parent() {
 		// Parse a simple document.
-		let tree = Tree::parse(HTML).expect("Tree parse failed.");
+		let tree = Tree::parse(HTML, &MinifyOptions::default()).expect("Tree parse failed.");
 
 		// Find the span.
 		let target = Rc::clone(
@@ -923,7 +1148,7 @@ mod tests {
 	#[test]
 	fn t_append_before_sibling() {
 		// Parse a simple document.
-		let tree = Tree::parse(HTML).expect("Tree parse failed.");
+		let tree = Tree::parse(HTML, &MinifyOptions::default()).expect("Tree parse failed.");
 
 		// Find the span.
 		let target = Rc::clone(
@@ -951,6 +1176,121 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn t_options() {
+		const RAW: &[u8] = b"<html><body><!-- Hi! --><my-el>  a  </my-el><pre-el>  b  <i>  c  </i></pre-el></body></html>";
+
+		// Defaults leave the custom elements alone, and drop comments.
+		let tree = Tree::parse(RAW, &MinifyOptions::default()).expect("Tree parse failed.");
+		assert_eq!(
+			tree.serialize(None).expect("Serialize failed."),
+			"<!DOCTYPE html>\n<html>\n<head></head>\n<body>\n<my-el>  a  </my-el>\n<pre-el>  b  <i> c </i></pre-el>\n</body>\n</html>",
+		);
+
+		// Now with some opinions.
+		let opts = MinifyOptions {
+			comments: true,
+			collapse: vec![LocalName::from("my-el")],
+			preserve: vec![LocalName::from("pre-el")],
+			..MinifyOptions::default()
+		};
+		let tree = Tree::parse(RAW, &opts).expect("Tree parse failed.");
+		assert_eq!(
+			tree.serialize(None).expect("Serialize failed."),
+			"<!DOCTYPE html>\n<html>\n<head></head>\n<body><!-- Hi! -->\n<my-el> a </my-el>\n<pre-el>  b  <i>  c  </i></pre-el>\n</body>\n</html>",
+		);
+	}
+
+	#[test]
+	fn t_presets() {
+		const RAW: &[u8] = br#"<html><body> <p class=" a  b ">  hi  </p><script type="text/javascript"> x </script><input disabled="disabled" value=""></body></html>"#;
+
+		for (opts, expected) in [
+			(
+				MinifyOptions::default(),
+				"<!DOCTYPE html>\n<html>\n<head></head>\n<body>\n<p class=\"a b\"> hi </p>\n<script>x</script>\n<input disabled value>\n</body>\n</html>",
+			),
+			(
+				MinifyOptions::safe(),
+				"<!DOCTYPE html>\n<html>\n<head></head>\n<body>\n<p class=\" a  b \"> hi </p>\n<script type=\"text/javascript\"> x </script>\n<input disabled=\"disabled\" value=\"\">\n</body>\n</html>",
+			),
+			(
+				MinifyOptions::aggressive(),
+				"<!DOCTYPE html><html><head></head><body><p class=\"a b\"> hi </p><script>x</script><input disabled value></body></html>",
+			),
+			(
+				MinifyOptions::default().with_collapse_whitespace(false).with_trim_whitespace(false),
+				"<!DOCTYPE html>\n<html>\n<head></head>\n<body> \n<p class=\"a b\">  hi  </p>\n<script> x </script>\n<input disabled value>\n</body>\n</html>",
+			),
+		] {
+			let tree = Tree::parse(RAW, &opts).expect("Tree parse failed.");
+			assert_eq!(tree.serialize(None).expect("Serialize failed."), expected);
+		}
+	}
+
+	#[test]
+	fn t_passes() {
+		/// # Append to Text.
+		struct Append(&'static str, Stage);
+		impl Pass for Append {
+			fn stage(&self) -> Stage { self.1 }
+			fn text(&self, text: &Text<'_>) {
+				if text.parent() == Some("p") { text.set(&format!("{}{}", text.get(), self.0)); }
+			}
+		}
+
+		/// # Attribute Shuffling.
+		struct Attrs;
+		impl Pass for Attrs {
+			fn element(&self, el: &Element<'_>) {
+				if el.is_html() && el.name() == "p" {
+					assert_eq!(el.attr("DATA-TEST").as_deref(), Some("x"));
+					el.retain_attrs(|k, _| ! k.starts_with("data-"));
+					assert!(! el.has_attr("data-test"));
+					el.set_attr("id", "hi");
+				}
+			}
+		}
+
+		const RAW: &[u8] = b"<html><body><p data-test=x data-foo=y class=a>Hi</p></body></html>";
+
+		// Same-stage passes run in order; before-minify text gets minified.
+		let opts = MinifyOptions::aggressive()
+			.with_pass(Append("  1", Stage::AfterMinify))
+			.with_pass(Append("  2", Stage::BeforeMinify))
+			.with_pass(Attrs)
+			.with_pass(Append("  3", Stage::BeforeMinify));
+		let tree = Tree::parse(RAW, &opts).expect("Tree parse failed.");
+		assert_eq!(
+			tree.serialize(None).expect("Serialize failed."),
+			"<!DOCTYPE html><html><head></head><body><p class=\"a\" id=\"hi\">Hi 2 3  1</p></body></html>",
+		);
+	}
+
+	#[test]
+	fn t_savings() {
+		const RAW: &[u8] = br#"<!DOCTYPE html PUBLIC "x"><html><body><!--abc--><p class="  a  b ">  hi   there</p><script type="text/javascript"></script><input disabled="disabled"><div id="" title='say "hi"'></div></body></html>"#;
+
+		let tree = Tree::parse(RAW, &MinifyOptions::default()).expect("Tree parse failed.");
+		assert_eq!(
+			tree.serialize(None).expect("Serialize failed."),
+			"<!DOCTYPE html>\n<html>\n<head></head>\n<body>\n<p class=\"a b\"> hi there</p>\n<script></script>\n<input disabled>\n<div id title='say \"hi\"'></div>\n</body>\n</html>",
+		);
+
+		let savings = tree.savings();
+		for (kind, expected) in [
+			(Category::Whitespace, 7),
+			(Category::Comments, 10),
+			(Category::TypeAttrs, 23),
+			(Category::BooleanAttrs, 11),
+			(Category::EmptyAttrs, 3),
+			(Category::Quotes, 8),
+			(Category::Doctype, 11),
+		] {
+			assert_eq!(savings.get(kind), expected, "{}", kind.label());
+		}
+	}
+
 	#[test]
 	fn t_can_collapse_whitespace() {
 		// The collapseable list is really big, but should _not_ include
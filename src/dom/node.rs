@@ -5,6 +5,13 @@ This module includes the node-related business, but most of it is dedicated
 to serialization/formatting.
 */
 
+use super::{
+	options::MinifyOptions,
+	savings::{
+		Category,
+		Savings,
+	},
+};
 use html5ever::{
 	local_name,
 	ns,
@@ -13,7 +20,10 @@ use html5ever::{
 };
 use indexmap::IndexMap;
 use std::{
-	cell::RefCell,
+	cell::{
+		Cell,
+		RefCell,
+	},
 	fmt,
 	rc::Rc,
 };
@@ -79,10 +89,18 @@ pub(crate) enum NodeInner {
 		contents: RefCell<StrTendril>
 	},
 
+	/// # Comment.
+	///
+	/// Comments are only kept when explicitly requested.
+	Comment {
+		/// # Content.
+		contents: StrTendril,
+	},
+
 	/// # Comments, Doctypes, Processing Instructions.
 	///
-	/// We don't support these node types, but the `TreeSink` API requires we
-	/// "create" them anyway.
+	/// We don't support these node types (or don't want them), but the
+	/// `TreeSink` API requires we "create" them anyway.
 	Ignored,
 }
 
@@ -92,15 +110,24 @@ pub(crate) enum NodeInner {
 ///
 /// This wrapper is used for serialization/display of a `Node` and its
 /// children.
-pub(super) struct NodeDisplay {
+///
+/// Along the way, the bytes saved by the various serialization shortcuts are
+/// tallied up in `savings`.
+pub(super) struct NodeDisplay<'a> {
 	/// # Parent Element (if any).
 	parent: Option<QualName>,
 
 	/// # The Current Object.
 	node: Handle,
+
+	/// # Options.
+	opts: &'a MinifyOptions,
+
+	/// # Savings.
+	savings: &'a Cell<Savings>,
 }
 
-impl fmt::Display for NodeDisplay {
+impl fmt::Display for NodeDisplay<'_> {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		use std::fmt::Write;
 
@@ -108,11 +135,15 @@ impl fmt::Display for NodeDisplay {
 			// New document!
 			NodeInner::Document => {
 				// Write the DOCTYPE.
-				f.write_str("<!DOCTYPE html>\n")?;
+				f.write_str(super::DOCTYPE)?;
+				if self.opts.newlines { f.write_char('\n')?; }
 
 				// Recurse children.
 				for child in self.node.children.borrow().iter() {
-					<Self as fmt::Display>::fmt(&Self::new(child, None), f)?;
+					<Self as fmt::Display>::fmt(
+						&Self::new(child, None, self.opts, self.savings),
+						f,
+					)?;
 				}
 
 				Ok(())
@@ -132,6 +163,8 @@ impl fmt::Display for NodeDisplay {
 							tag: name,
 							key,
 							value: value.as_ref(),
+							opts: self.opts,
+							savings: self.savings,
 						},
 						f,
 					)?;
@@ -155,12 +188,16 @@ impl fmt::Display for NodeDisplay {
 
 				// Recurse children.
 				for child in self.node.children.borrow().iter() {
-					<Self as fmt::Display>::fmt(&Self::new(child, Some(name.clone())), f)?;
+					<Self as fmt::Display>::fmt(
+						&Self::new(child, Some(name.clone()), self.opts, self.savings),
+						f,
+					)?;
 				}
 
 				// Move <body>/<html> closures to their own line, again for
 				// readability.
 				if
+					self.opts.newlines &&
 					matches!(name.ns, ns!(html)) &&
 					matches!(name.local, local_name!("body") | local_name!("html"))
 				{
@@ -184,21 +221,32 @@ impl fmt::Display for NodeDisplay {
 				}
 			},
 
+			// Comments pass through as-is.
+			NodeInner::Comment { ref contents } =>
+				write!(f, "<!--{}-->", contents.as_ref()),
+
 			// Don't care.
 			NodeInner::Ignored => Ok(()),
 		}
 	}
 }
 
-impl NodeDisplay {
+impl<'a> NodeDisplay<'a> {
 	#[must_use]
 	/// # New.
 	///
 	/// Create and return a new display wrapper given the `node` and `children`.
-	pub(super) fn new(node: &Handle, parent: Option<QualName>) -> Self {
+	pub(super) fn new(
+		node: &Handle,
+		parent: Option<QualName>,
+		opts: &'a MinifyOptions,
+		savings: &'a Cell<Savings>,
+	) -> Self {
 		Self {
 			parent,
 			node: Rc::clone(node),
+			opts,
+			savings,
 		}
 	}
 
@@ -206,9 +254,10 @@ impl NodeDisplay {
 	/// # New Line Before Opening?
 	///
 	/// Direct children of `<html`> and `<body>` are given a new line to
-	/// improve readability, at the cost of a couple extra bytes.
+	/// improve readability, at the cost of a couple extra bytes, unless
+	/// disabled by the options.
 	const fn line_before_open(&self) -> bool {
-		if let Some(parent) = self.parent.as_ref() {
+		if self.opts.newlines && let Some(parent) = self.parent.as_ref() {
 			matches!(parent.ns, ns!(html)) &&
 			matches!(parent.local, local_name!("body") | local_name!("html"))
 		}
@@ -249,6 +298,12 @@ struct AttrDisplay<'a> {
 
 	/// # Attribute Value.
 	value: &'a str,
+
+	/// # Options.
+	opts: &'a MinifyOptions,
+
+	/// # Savings.
+	savings: &'a Cell<Savings>,
 }
 
 impl fmt::Display for AttrDisplay<'_> {
@@ -258,6 +313,7 @@ impl fmt::Display for AttrDisplay<'_> {
 		// We can skip type="text/css" and type="text/javascript" on
 		// style and script blocks, respectively.
 		if
+			self.opts.elide_types &&
 			matches!(self.key.ns, ns!()) &&
 			matches!(self.key.local, local_name!("type")) &&
 			match self.tag.local {
@@ -266,6 +322,12 @@ impl fmt::Display for AttrDisplay<'_> {
 				_ => false,
 			}
 		{
+			// A space, the key, an equal sign, the value, and two quotes.
+			Savings::add(
+				self.savings,
+				Category::TypeAttrs,
+				self.key.local.len() + self.value.len() + 4,
+			);
 			return Ok(());
 		}
 
@@ -285,17 +347,26 @@ impl fmt::Display for AttrDisplay<'_> {
 		f.write_str(self.key.local.as_ref())?;
 
 		// If this is a boolean HTML key, we're done.
-		if self.is_boolean() { return Ok(()); }
+		if self.opts.elide_booleans && self.is_boolean() {
+			// An equal sign, the value, and two quotes.
+			Savings::add(self.savings, Category::BooleanAttrs, self.value.len() + 3);
+			return Ok(());
+		}
 
 		// Figure out the best quoting style for the value.
 		let v = AttrValueDisplay::new(
 			self.value,
-			WhitespaceNormalization::new(self.tag, self.key),
+			if self.opts.trim_attrs { WhitespaceNormalization::new(self.tag, self.key) }
+			else { WhitespaceNormalization::None },
 		);
+		v.tally(self.value, self.savings);
 		if
+			self.opts.elide_empty &&
 			matches!(self.tag.ns, ns!(html)) &&
 			matches!(self.key.ns, ns!()) &&
 			v.is_empty() {
+			// An equal sign and two quotes.
+			Savings::add(self.savings, Category::EmptyAttrs, 3);
 			return Ok(());
 		}
 
@@ -463,6 +534,44 @@ impl<'a> AttrValueDisplay<'a> {
 	#[must_use]
 	/// # Is Empty?
 	const fn is_empty(&self) -> bool { matches!(self, Self::Empty) }
+
+	/// # Tally Savings.
+	///
+	/// Add up the whitespace trimmed and collapsed from the `raw` value, as
+	/// well as the escapes avoided by the choice of quotes.
+	fn tally(&self, raw: &str, savings: &Cell<Savings>) {
+		let (v, ws, single) = match *self {
+			Self::Double(v, ws) => (v, ws, false),
+			Self::Single(v, ws) => (v, ws, true),
+			Self::Empty => {
+				Savings::add(savings, Category::Whitespace, raw.len());
+				return;
+			},
+		};
+
+		let mut collapsed = 0;
+		let mut doubles = 0;
+		let mut singles = 0;
+		let mut in_ws = false;
+		for b in v.bytes() {
+			match b {
+				b'\t' | b'\n' | b'\x0C' | b' ' => {
+					if in_ws && ws.collapse() { collapsed += 1; }
+					in_ws = true;
+					continue;
+				},
+				b'"' => { doubles += 1; },
+				b'\'' => { singles += 1; },
+				_ => {},
+			}
+			in_ws = false;
+		}
+
+		Savings::add(savings, Category::Whitespace, raw.len() - v.len() + collapsed);
+
+		// Each escape — &#34; or &#39; — costs four extra bytes.
+		if single { Savings::add(savings, Category::Quotes, (doubles - singles) * 4); }
+	}
 }
 
 
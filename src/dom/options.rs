@@ -0,0 +1,261 @@
+/*!
+# HTMinL: Minification Options.
+*/
+
+use html5ever::{
+	LocalName,
+	ns,
+	QualName,
+};
+use super::pass::{
+	Pass,
+	Passes,
+};
+use std::sync::Arc;
+
+
+
+#[expect(clippy::struct_excessive_bools, reason = "They're independent toggles.")]
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// # Minification Options.
+///
+/// This holds the user-configurable knobs affecting how a document is
+/// minified. The defaults match HTMinL's historical behaviors, but there are
+/// also a couple of named presets — `MinifyOptions::safe` and
+/// `MinifyOptions::aggressive` — for those wanting less or more.
+///
+/// ## Examples
+///
+/// ```
+/// use htminl::MinifyOptions;
+///
+/// let opts = MinifyOptions::default()
+///     .with_comments(true)
+///     .with_elide_types(false)
+///     .with_preserve(["my-code"]);
+/// ```
+pub struct MinifyOptions {
+	/// # Preserve Comments?
+	pub(crate) comments: bool,
+
+	/// # Extra Collapsible Elements.
+	///
+	/// Custom elements whose text whitespace can be collapsed like a regular
+	/// `<div>`.
+	pub(crate) collapse: Vec<LocalName>,
+
+	/// # Preserved Elements.
+	///
+	/// Elements whose contents — including any descendants — should be left
+	/// exactly as-is.
+	pub(crate) preserve: Vec<LocalName>,
+
+	/// # Collapse/Drop Text Whitespace?
+	pub(crate) collapse_whitespace: bool,
+
+	/// # Trim First/Last Text?
+	pub(crate) trim_whitespace: bool,
+
+	/// # Readability Newlines?
+	pub(crate) newlines: bool,
+
+	/// # Elide Default `type` Attributes?
+	pub(crate) elide_types: bool,
+
+	/// # Elide Boolean Attribute Values?
+	pub(crate) elide_booleans: bool,
+
+	/// # Elide Empty Attribute Values?
+	pub(crate) elide_empty: bool,
+
+	/// # Trim/Collapse Attribute Values?
+	pub(crate) trim_attrs: bool,
+
+	/// # Custom Passes.
+	pub(crate) passes: Passes,
+}
+
+impl Default for MinifyOptions {
+	fn default() -> Self {
+		Self {
+			comments: false,
+			collapse: Vec::new(),
+			preserve: Vec::new(),
+			collapse_whitespace: true,
+			trim_whitespace: true,
+			newlines: true,
+			elide_types: true,
+			elide_booleans: true,
+			elide_empty: true,
+			trim_attrs: true,
+			passes: Passes::default(),
+		}
+	}
+}
+
+impl MinifyOptions {
+	#[must_use]
+	/// # Safe Preset.
+	///
+	/// Stick to whitespace collapsing and comment removal, leaving text
+	/// edges and attributes exactly as written.
+	pub fn safe() -> Self {
+		Self::default()
+			.with_trim_whitespace(false)
+			.with_elide_types(false)
+			.with_elide_booleans(false)
+			.with_elide_empty(false)
+			.with_trim_attrs(false)
+	}
+
+	#[must_use]
+	/// # Aggressive Preset.
+	///
+	/// Same as the default, but without the extra line breaks normally
+	/// added around `<html>`/`<body>` children for readability.
+	pub fn aggressive() -> Self { Self::default().with_newlines(false) }
+
+	#[must_use]
+	/// # With Comments.
+	///
+	/// Keep `<!-- comments -->` instead of stripping them.
+	pub const fn with_comments(mut self, comments: bool) -> Self {
+		self.comments = comments;
+		self
+	}
+
+	#[must_use]
+	/// # With Collapsible Elements.
+	///
+	/// Allow the whitespace inside these custom elements to be collapsed like
+	/// that of a regular `<div>`. Tag names are case-insensitive.
+	pub fn with_collapse<I, S>(mut self, tags: I) -> Self
+	where I: IntoIterator<Item=S>, S: AsRef<str> {
+		self.collapse = local_names(tags);
+		self
+	}
+
+	#[must_use]
+	/// # With Preserved Elements.
+	///
+	/// Leave the contents of these elements — including any descendants —
+	/// exactly as-is. Tag names are case-insensitive.
+	pub fn with_preserve<I, S>(mut self, tags: I) -> Self
+	where I: IntoIterator<Item=S>, S: AsRef<str> {
+		self.preserve = local_names(tags);
+		self
+	}
+
+	#[must_use]
+	/// # With Whitespace Collapsing.
+	///
+	/// Collapse contiguous whitespace in text nodes, and drop whitespace-only
+	/// text nodes where they serve no purpose.
+	pub const fn with_collapse_whitespace(mut self, collapse: bool) -> Self {
+		self.collapse_whitespace = collapse;
+		self
+	}
+
+	#[must_use]
+	/// # With Whitespace Trimming.
+	///
+	/// Trim the leading/trailing whitespace from the first/last text children
+	/// of structural elements like `<body>`, `<script>`, and `<title>`.
+	pub const fn with_trim_whitespace(mut self, trim: bool) -> Self {
+		self.trim_whitespace = trim;
+		self
+	}
+
+	#[must_use]
+	/// # With Readability Newlines.
+	///
+	/// Put the direct children of `<html>` and `<body>` (and the doctype)
+	/// on their own lines, at the cost of a few extra bytes.
+	pub const fn with_newlines(mut self, newlines: bool) -> Self {
+		self.newlines = newlines;
+		self
+	}
+
+	#[must_use]
+	/// # With `type` Elision.
+	///
+	/// Drop `type="text/javascript"` from `<script>` and `type="text/css"`
+	/// from `<style>`, as those are implied.
+	pub const fn with_elide_types(mut self, elide: bool) -> Self {
+		self.elide_types = elide;
+		self
+	}
+
+	#[must_use]
+	/// # With Boolean Elision.
+	///
+	/// Drop the (implied) values of boolean HTML attributes, e.g.
+	/// `disabled="disabled"` becomes `disabled`.
+	pub const fn with_elide_booleans(mut self, elide: bool) -> Self {
+		self.elide_booleans = elide;
+		self
+	}
+
+	#[must_use]
+	/// # With Empty Elision.
+	///
+	/// Drop the `=""` from empty HTML attribute values.
+	pub const fn with_elide_empty(mut self, elide: bool) -> Self {
+		self.elide_empty = elide;
+		self
+	}
+
+	#[must_use]
+	/// # With Attribute Trimming.
+	///
+	/// Trim and/or collapse the whitespace in attribute values where it is
+	/// insignificant, e.g. `class` and `href`.
+	pub const fn with_trim_attrs(mut self, trim: bool) -> Self {
+		self.trim_attrs = trim;
+		self
+	}
+
+	#[must_use]
+	/// # With Custom Pass.
+	///
+	/// Add a custom `Pass` to run alongside the built-in ones. Passes sharing
+	/// a `Stage` run in the order they were added.
+	pub fn with_pass<P: Pass + 'static>(mut self, pass: P) -> Self {
+		self.passes.push(Arc::new(pass));
+		self
+	}
+
+	#[must_use]
+	/// # Can Collapse Custom Element?
+	///
+	/// Returns `true` if `tag` is an HTML element explicitly marked as
+	/// collapsible.
+	pub(super) fn is_collapsible(&self, tag: &QualName) -> bool {
+		matches!(tag.ns, ns!(html)) && self.collapse.contains(&tag.local)
+	}
+
+	#[must_use]
+	/// # Is Preserved Element?
+	///
+	/// Returns `true` if `tag` is an HTML element whose contents should be
+	/// left alone.
+	pub(super) fn is_preserved(&self, tag: &QualName) -> bool {
+		matches!(tag.ns, ns!(html)) && self.preserve.contains(&tag.local)
+	}
+}
+
+
+
+/// # Local Names.
+///
+/// Convert tag names to (lowercase, deduplicated) `LocalName`s.
+fn local_names<I, S>(src: I) -> Vec<LocalName>
+where I: IntoIterator<Item=S>, S: AsRef<str> {
+	let mut out: Vec<LocalName> = src.into_iter()
+		.map(|v| LocalName::from(v.as_ref().trim().to_ascii_lowercase()))
+		.filter(|v| ! v.is_empty())
+		.collect();
+	out.sort_unstable();
+	out.dedup();
+	out
+}
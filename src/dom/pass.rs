@@ -0,0 +1,288 @@
+/*!
+# HTMinL: Custom Passes.
+*/
+
+use html5ever::{
+	LocalName,
+	ns,
+	QualName,
+	tendril::StrTendril,
+};
+use indexmap::IndexMap;
+use std::{
+	cell::RefCell,
+	fmt,
+	sync::Arc,
+};
+
+
+
+/// # Custom Pass.
+///
+/// Implement this trait to transform documents in ways HTMinL doesn't do on
+/// its own — stripping test attributes, rewriting CDN URLs, etc. — then add
+/// it to the `MinifyOptions` with `MinifyOptions::with_pass`.
+///
+/// Each pass walks the entire tree, visiting every element and text node in
+/// document order. Passes sharing a `Stage` run in the order they were
+/// added, each seeing the results of the last.
+///
+/// ## Examples
+///
+/// ```
+/// use htminl::{Element, MinifyOptions, Pass};
+///
+/// /// # Strip `data-test` Attributes.
+/// struct NoTests;
+///
+/// impl Pass for NoTests {
+///     fn element(&self, el: &Element<'_>) { el.remove_attr("data-test"); }
+/// }
+///
+/// let mut raw = String::from(r#"<p data-test="x">Hello</p>"#);
+/// let opts = MinifyOptions::default().with_pass(NoTests);
+/// let out = htminl::minify_html(&mut raw, &opts).unwrap();
+/// assert_eq!(out.html.as_deref(), Some("<p>Hello</p>"));
+/// ```
+pub trait Pass: Send + Sync {
+	/// # Name.
+	///
+	/// This is used for debugging. It defaults to the type name.
+	fn name(&self) -> &str { std::any::type_name::<Self>() }
+
+	/// # Stage.
+	///
+	/// When to run the pass, relative to the built-in ones.
+	fn stage(&self) -> Stage { Stage::default() }
+
+	/// # Visit Element.
+	fn element(&self, _el: &Element<'_>) {}
+
+	/// # Visit Text.
+	fn text(&self, _text: &Text<'_>) {}
+}
+
+
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+/// # Pass Stage.
+///
+/// Custom passes run after the tree has been parsed and cleaned up, either
+/// before or after the built-in whitespace minification.
+///
+/// Either way, the attribute-related optimizations — `type` elision and
+/// such — happen later, during serialization.
+pub enum Stage {
+	#[default]
+	/// # Before Whitespace Minification.
+	///
+	/// Text is seen as written, and any text added will be minified.
+	BeforeMinify,
+
+	/// # After Whitespace Minification.
+	///
+	/// Text is seen as minified, and any text added will be kept as-is.
+	AfterMinify,
+}
+
+
+
+/// # Element.
+///
+/// A safe view of an element visited by a `Pass`, allowing its attributes
+/// to be read and changed.
+///
+/// Only un-namespaced attributes — i.e. pretty much all of them, except for
+/// the likes of `xlink:href` — are accessible. Keys are case-insensitive.
+pub struct Element<'a> {
+	/// # Tag Name.
+	name: &'a QualName,
+
+	/// # Attributes.
+	attrs: &'a RefCell<IndexMap<QualName, StrTendril>>,
+}
+
+impl<'a> Element<'a> {
+	#[must_use]
+	/// # New.
+	pub(super) const fn new(
+		name: &'a QualName,
+		attrs: &'a RefCell<IndexMap<QualName, StrTendril>>,
+	) -> Self {
+		Self { name, attrs }
+	}
+
+	#[must_use]
+	/// # Tag Name.
+	///
+	/// Return the (lowercase) tag name, e.g. `div`.
+	pub fn name(&self) -> &str { self.name.local.as_ref() }
+
+	#[must_use]
+	/// # Is HTML?
+	///
+	/// Returns `true` for HTML elements, or `false` for SVG/MathML ones.
+	pub fn is_html(&self) -> bool { matches!(self.name.ns, ns!(html)) }
+
+	#[must_use]
+	/// # Attribute Value.
+	pub fn attr(&self, key: &str) -> Option<String> {
+		self.attrs.borrow().get(&attr_key(key)).map(ToString::to_string)
+	}
+
+	#[must_use]
+	/// # Has Attribute?
+	pub fn has_attr(&self, key: &str) -> bool {
+		self.attrs.borrow().contains_key(&attr_key(key))
+	}
+
+	#[expect(clippy::must_use_candidate, reason = "The result is optional.")]
+	/// # Set Attribute.
+	///
+	/// Add the attribute, or replace the value of the existing one, returning
+	/// `true` if successful.
+	///
+	/// If the key isn't a valid attribute name — empty, or containing
+	/// whitespace, control characters, quotes, `/`, `<`, `=`, or `>` — the
+	/// change is dropped and `false` is returned instead.
+	pub fn set_attr(&self, key: &str, value: &str) -> bool {
+		let key = key.trim();
+		if
+			key.is_empty() ||
+			key.chars().any(|c|
+				c.is_whitespace() ||
+				c.is_control() ||
+				matches!(c, '"' | '\'' | '/' | '<' | '=' | '>')
+			)
+		{
+			return false;
+		}
+
+		self.attrs.borrow_mut().insert(attr_key(key), StrTendril::from(value));
+		true
+	}
+
+	/// # Remove Attribute.
+	pub fn remove_attr(&self, key: &str) {
+		self.attrs.borrow_mut().shift_remove(&attr_key(key));
+	}
+
+	/// # Retain Attributes.
+	///
+	/// Remove any attributes for which `cb(key, value)` returns `false`.
+	pub fn retain_attrs<F>(&self, mut cb: F)
+	where F: FnMut(&str, &str) -> bool {
+		self.attrs.borrow_mut().retain(|k, v|
+			! matches!(k.ns, ns!()) || cb(k.local.as_ref(), v.as_ref())
+		);
+	}
+}
+
+
+
+/// # Text.
+///
+/// A safe view of a text node visited by a `Pass`, allowing its contents
+/// to be read and changed.
+pub struct Text<'a> {
+	/// # Parent Element.
+	parent: Option<&'a QualName>,
+
+	/// # Contents.
+	contents: &'a RefCell<StrTendril>,
+}
+
+impl<'a> Text<'a> {
+	#[must_use]
+	/// # New.
+	pub(super) const fn new(
+		parent: Option<&'a QualName>,
+		contents: &'a RefCell<StrTendril>,
+	) -> Self {
+		Self { parent, contents }
+	}
+
+	#[must_use]
+	/// # Parent Tag Name.
+	pub fn parent(&self) -> Option<&str> { self.parent.map(|p| p.local.as_ref()) }
+
+	#[must_use]
+	/// # Contents.
+	///
+	/// Return the (unescaped) text.
+	pub fn get(&self) -> String { self.contents.borrow().to_string() }
+
+	/// # Set Contents.
+	///
+	/// Replace the text. It will be escaped as needed during serialization.
+	pub fn set(&self, text: &str) { *self.contents.borrow_mut() = StrTendril::from(text); }
+}
+
+
+
+#[derive(Clone, Default)]
+/// # Passes.
+///
+/// This holds the custom passes for `MinifyOptions`. It only really exists
+/// to paper over the lack of `Debug`/`Eq` for trait objects; passes are
+/// compared by identity.
+pub(crate) struct Passes(Vec<Arc<dyn Pass>>);
+
+impl fmt::Debug for Passes {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_list().entries(self.0.iter().map(|p| p.name())).finish()
+	}
+}
+
+impl Eq for Passes {}
+
+impl PartialEq for Passes {
+	fn eq(&self, other: &Self) -> bool {
+		self.0.len() == other.0.len() &&
+		self.0.iter().zip(&other.0).all(|(a, b)| Arc::ptr_eq(a, b))
+	}
+}
+
+impl Passes {
+	/// # Push.
+	pub(super) fn push(&mut self, pass: Arc<dyn Pass>) { self.0.push(pass); }
+
+	/// # Passes for Stage.
+	pub(super) fn stage(&self, stage: Stage) -> impl Iterator<Item=&dyn Pass> {
+		self.0.iter().map(AsRef::as_ref).filter(move |p| p.stage() == stage)
+	}
+}
+
+
+
+/// # Attribute Key.
+fn attr_key(key: &str) -> QualName {
+	QualName::new(None, ns!(), LocalName::from(key.trim().to_ascii_lowercase()))
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use html5ever::local_name;
+
+	#[test]
+	fn t_set_attr() {
+		let name = QualName::new(None, ns!(html), local_name!("a"));
+		let attrs = RefCell::new(IndexMap::new());
+		let el = Element::new(&name, &attrs);
+
+		// Keys are normalized.
+		assert!(el.set_attr(" HREF ", "/"));
+		assert_eq!(el.attr("href").as_deref(), Some("/"));
+		assert!(el.set_attr("data-x", ""));
+		assert!(el.set_attr("xlink:href", "#"));
+
+		// Nonsense is not.
+		for key in ["", " ", "a b", "a\tb", "a=b", "a\"b", "a'b", "a>b", "a<b", "a/b", "a\0b"] {
+			assert!(! el.set_attr(key, "x"), "{key:?}");
+		}
+		assert_eq!(attrs.borrow().len(), 3);
+	}
+}
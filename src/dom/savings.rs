@@ -0,0 +1,140 @@
+/*!
+# HTMinL: Savings Breakdown.
+*/
+
+use std::cell::Cell;
+
+
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+/// # Savings Category.
+///
+/// The (measurable) optimizations contributing to a document's savings.
+pub enum Category {
+	/// # Whitespace Collapsing/Trimming.
+	Whitespace,
+
+	/// # Dropped Comments.
+	Comments,
+
+	/// # Removed `type` Attributes.
+	TypeAttrs,
+
+	/// # Elided Boolean Attribute Values.
+	BooleanAttrs,
+
+	/// # Dropped Empty Attribute Values.
+	EmptyAttrs,
+
+	/// # Quote Selection.
+	Quotes,
+
+	/// # Doctype Rewriting.
+	Doctype,
+}
+
+impl Category {
+	/// # Number of Categories.
+	pub const COUNT: usize = 7;
+
+	/// # All Categories.
+	pub const ALL: [Self; Self::COUNT] = [
+		Self::Whitespace,
+		Self::Comments,
+		Self::TypeAttrs,
+		Self::BooleanAttrs,
+		Self::EmptyAttrs,
+		Self::Quotes,
+		Self::Doctype,
+	];
+
+	#[must_use]
+	/// # Label.
+	pub const fn label(self) -> &'static str {
+		match self {
+			Self::Whitespace => "Whitespace",
+			Self::Comments => "Comments",
+			Self::TypeAttrs => "Type attributes",
+			Self::BooleanAttrs => "Boolean attributes",
+			Self::EmptyAttrs => "Empty attributes",
+			Self::Quotes => "Attribute quotes",
+			Self::Doctype => "Doctype",
+		}
+	}
+}
+
+
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+/// # Savings Breakdown.
+///
+/// This holds the number of bytes saved by each `Category` of optimization
+/// for a given document.
+///
+/// Note: the values are measured against a plain serialization of the parsed
+/// tree, so won't necessarily add up to the total; the parser has a few
+/// tricks of its own, like dropping implied tags and decoding entities.
+pub struct Savings([u64; Category::COUNT]);
+
+impl Savings {
+	#[must_use]
+	/// # Get.
+	pub const fn get(&self, kind: Category) -> u64 { self.0[kind as usize] }
+
+	/// # Add.
+	pub(super) fn add(cell: &Cell<Self>, kind: Category, bytes: usize) {
+		if bytes != 0 {
+			let mut tmp = cell.get();
+			tmp.0[kind as usize] = tmp.0[kind as usize].saturating_add(bytes as u64);
+			cell.set(tmp);
+		}
+	}
+}
+
+
+
+#[must_use]
+/// # Doctype Length.
+///
+/// Return the length of the `<!DOCTYPE …>` declaration opening the raw
+/// document, if any, ignoring leading whitespace.
+pub(super) fn doctype_len(raw: &[u8]) -> usize {
+	let raw = raw.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(raw).trim_ascii_start();
+	if raw.len() > 9 && raw[..9].eq_ignore_ascii_case(b"<!doctype") {
+		raw.iter().position(|&b| b == b'>').map_or(0, |pos| pos + 1)
+	}
+	else { 0 }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn t_doctype_len() {
+		for (raw, expected) in [
+			(&b"<!DOCTYPE html><html></html>"[..], 15),
+			(b"  \n<!doctype HTML>", 15),
+			(b"\xEF\xBB\xBF<!DOCTYPE html>", 15),
+			(b"<!DOCTYPE html PUBLIC \"-//W3C//DTD XHTML 1.0 Strict//EN\">", 57),
+			(b"<html></html>", 0),
+			(b"<!DOCTYPE html", 0),
+		] {
+			assert_eq!(doctype_len(raw), expected, "{:?}", String::from_utf8_lossy(raw));
+		}
+	}
+
+	#[test]
+	fn t_add() {
+		let cell = Cell::new(Savings::default());
+
+		Savings::add(&cell, Category::Quotes, 4);
+		Savings::add(&cell, Category::Quotes, 4);
+		Savings::add(&cell, Category::Doctype, 0);
+		assert_eq!(cell.get().get(Category::Quotes), 8);
+		assert_eq!(cell.get().get(Category::Doctype), 0);
+		assert_eq!(cell.get().get(Category::Whitespace), 0);
+	}
+}
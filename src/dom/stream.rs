@@ -0,0 +1,116 @@
+/*!
+# HTMinL: Streaming Output.
+*/
+
+use std::{
+	fmt,
+	io::{
+		self,
+		BufWriter,
+		Write,
+	},
+};
+
+
+
+/// # I/O Formatter.
+///
+/// This adapts a (buffered) `io::Write` into a `fmt::Write`, allowing
+/// `NodeDisplay` to be written straight into files, sockets, encoders, etc.,
+/// without first collecting everything into a `String`.
+///
+/// Because `fmt::Error` carries no details, the first I/O error is held onto
+/// so it can be told apart from genuine formatting failures.
+pub(super) struct IoFmt<W: Write> {
+	/// # Writer.
+	inner: BufWriter<W>,
+
+	/// # Bytes Written.
+	written: u64,
+
+	/// # I/O Error (if any).
+	error: Option<io::ErrorKind>,
+}
+
+impl<W: Write> fmt::Write for IoFmt<W> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		match self.inner.write_all(s.as_bytes()) {
+			Ok(()) => {
+				self.written += s.len() as u64;
+				Ok(())
+			},
+			Err(e) => {
+				if self.error.is_none() { self.error = Some(e.kind()); }
+				Err(fmt::Error)
+			},
+		}
+	}
+}
+
+impl<W: Write> IoFmt<W> {
+	#[must_use]
+	/// # New.
+	pub(super) fn new(writer: W) -> Self {
+		Self {
+			inner: BufWriter::new(writer),
+			written: 0,
+			error: None,
+		}
+	}
+
+	#[must_use]
+	/// # I/O Error.
+	///
+	/// Return the kind of the first I/O error encountered, if any.
+	pub(super) const fn error(&self) -> Option<io::ErrorKind> { self.error }
+
+	/// # Finish.
+	///
+	/// Flush the buffer, returning the total number of bytes written.
+	///
+	/// ## Errors
+	///
+	/// This will return an error if the flush fails.
+	pub(super) fn finish(mut self) -> Result<u64, io::ErrorKind> {
+		self.inner.flush().map_err(|e| e.kind())?;
+		Ok(self.written)
+	}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::fmt::Write as _;
+
+	/// # Writer That Gives Up.
+	struct Broken;
+
+	impl Write for Broken {
+		fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+			Err(io::ErrorKind::BrokenPipe.into())
+		}
+		fn flush(&mut self) -> io::Result<()> { Ok(()) }
+	}
+
+	#[test]
+	fn t_io_fmt() {
+		let mut out = Vec::new();
+		let mut w = IoFmt::new(&mut out);
+		let name = "Hello";
+		write!(&mut w, "<p>{name}</p>").expect("Write failed.");
+		assert_eq!(w.error(), None);
+		assert_eq!(w.finish(), Ok(12));
+		assert_eq!(out, b"<p>Hello</p>");
+
+		// Errors surface on flush, or sooner if the buffer fills up.
+		let mut w = IoFmt::new(Broken);
+		write!(&mut w, "<p>Hello</p>").expect("Write failed.");
+		assert_eq!(w.finish(), Err(io::ErrorKind::BrokenPipe));
+
+		let mut w = IoFmt::new(Broken);
+		assert!(w.write_str(&"a".repeat(16 * 1024)).is_err());
+		assert_eq!(w.error(), Some(io::ErrorKind::BrokenPipe));
+	}
+}
@@ -0,0 +1,100 @@
+/*!
+# HTMinL: Phase Timings.
+*/
+
+use std::{
+	cell::Cell,
+	time::{
+		Duration,
+		Instant,
+	},
+};
+
+
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+/// # Processing Phase.
+pub enum Phase {
+	/// # Reading the Source.
+	Read,
+
+	/// # Parsing (`Tree::parse`).
+	Parse,
+
+	/// # Post-Processing.
+	PostProcess,
+
+	/// # Minifying.
+	Minify,
+
+	/// # Serializing.
+	Serialize,
+
+	/// # Writing the Result.
+	Write,
+}
+
+impl Phase {
+	/// # Number of Phases.
+	pub const COUNT: usize = 6;
+
+	/// # All Phases.
+	pub const ALL: [Self; Self::COUNT] = [
+		Self::Read,
+		Self::Parse,
+		Self::PostProcess,
+		Self::Minify,
+		Self::Serialize,
+		Self::Write,
+	];
+
+	#[must_use]
+	/// # Label.
+	pub const fn label(self) -> &'static str {
+		match self {
+			Self::Read => "read",
+			Self::Parse => "parse",
+			Self::PostProcess => "post_process",
+			Self::Minify => "minify",
+			Self::Serialize => "serialize",
+			Self::Write => "write",
+		}
+	}
+}
+
+
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+/// # Phase Timings.
+///
+/// This holds the time spent in each `Phase` for a given document.
+pub struct Timings([Duration; Phase::COUNT]);
+
+impl Timings {
+	#[must_use]
+	/// # Get.
+	pub const fn get(&self, phase: Phase) -> Duration { self.0[phase as usize] }
+
+	/// # Add.
+	///
+	/// Add the time elapsed since `start` to the `phase` total.
+	pub fn add(&mut self, phase: Phase, start: Instant) {
+		self.0[phase as usize] += start.elapsed();
+	}
+
+	/// # Add (Cell).
+	///
+	/// Same as `Timings::add`, but for shared references.
+	pub(super) fn add_cell(cell: &Cell<Self>, phase: Phase, start: Instant) {
+		let mut tmp = cell.get();
+		tmp.add(phase, start);
+		cell.set(tmp);
+	}
+
+	/// # Merge.
+	///
+	/// Add the timings from `other` to these.
+	pub fn merge(&mut self, other: Self) {
+		for (a, b) in self.0.iter_mut().zip(other.0) { *a += b; }
+	}
+}
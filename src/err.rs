@@ -2,14 +2,22 @@
 # HTMinL: Errors
 */
 
+use dactyl::traits::NiceInflection;
 use fyi_msg::fyi_ansi::{
 	ansi,
 	csi,
 	dim,
 };
+use htminl::MinifyError;
 use std::{
+	borrow::Cow,
 	error::Error,
 	fmt,
+	io,
+	path::{
+		Path,
+		PathBuf,
+	},
 };
 
 
@@ -25,19 +33,137 @@ const HELP: &str = concat!(r"
 
 USAGE:
     htminl [FLAGS] [OPTIONS] <PATH(S)>...
+    htminl [FLAGS] - < input.html > output.html
 
 FLAGS:
+        --backup      Save a copy of each original document — e.g.
+                      index.html.orig — before overwriting it. Existing
+                      backups are kept unless the document has since been
+                      changed by something other than HTMinL. Use
+                      --backup=<DIR> to save them to a mirrored directory
+                      instead.
+        --breakdown   Break down the bytes saved by category — whitespace,
+                      comments, attributes, etc. — in the summary.
+        --check       Minify documents without saving the results, listing
+                      any that would have changed. If there are any, the
+                      program will exit with a non-zero status.
+                      [alias: --dry-run]
+        --diff        Print a unified diff of the changes minification
+                      would make to each document instead of saving them.
+                      Implies --check.
+        --diff-pretty Same as --diff, but with line breaks added before
+                      each tag to make the changes easier to follow.
+        --ext-only    Only crawl files with the --ext extension(s), ignoring
+                      the default .htm/.html.
+        --gitignore   Honor any .gitignore and .ignore files found in the
+                      crawled directories.
     -h, --help        Print help information and exit.
+        --if-changed  Only (re)write output files whose contents would
+                      actually change, leaving identical ones — and their
+                      modification times — alone.
+        --incremental Skip documents that haven't changed since the last
+                      run, tracking them in a ./.htminl-cache manifest.
+                      (Changes to the applicable htminl.toml settings,
+                      output-affecting flags like --precompress, or HTMinL
+                      itself, count too, as do missing --precompress
+                      copies.)
+        --no-config   Ignore any htminl.toml configuration files.
+        --preserve-times
+                      Keep the original access/modification times on
+                      minified files. (Permissions and ownership are always
+                      preserved.)
+        --profile     Time each phase — read, parse, post_process, minify,
+                      serialize, write — of each document, listing the
+                      slowest documents and phase totals at the end.
     -p, --progress    Show progress bar while minifying.
+        --restore     Put the --backup copies of the crawled documents back,
+                      removing the backups.
+        --sniff       Inspect the first few bytes of files with unknown (or
+                      missing) extensions, crawling any that open with an
+                      HTML doctype or <html> tag.
+        --stdout      Read a single document from STDIN and print the
+                      minified result to STDOUT instead of crunching files
+                      in-place. A lone "-" may be passed instead.
+        --strict      Treat skipped documents as a fatal error.
     -V, --version     Print program version and exit.
+        --watch       After the initial pass, keep watching the directories
+                      for new or modified documents, minifying them as they
+                      change. Press CTRL+C to quit. (Linux only.)
 
 OPTIONS:
+        --cache <FILE>
+                      Same as --incremental, but with a custom manifest path.
+        --exclude <GLOB>
+                      Skip documents matching this pattern. Patterns are
+                      tested against the file name, the path relative to the
+                      crawled directory, and the full path. This option may
+                      be repeated.
+        --ext <EXT>   Crawl files with this extension too, in addition to
+                      .htm/.html. Multiple extensions may be comma-separated,
+                      or the option repeated.
+        --include <GLOB>
+                      Only crunch documents matching this pattern (or any of
+                      them, if repeated). Exclusions take priority.
+    -j, --jobs <NUM>  Use (at most) this many worker threads. [default: the
+                      number of available CPUs]
     -l, --list <FILE> Read (absolute) file and/or directory paths from this
                       text file — or STDIN if "-" — one entry per line, instead
                       of or in addition to the trailing <PATH(S)>.
+        --max-file-size <SIZE>
+                      Skip documents larger than this many bytes. A K, M, or
+                      G suffix may be used for larger units, e.g. "50M".
+        --out-dir <DIR>
+                      Save minified copies to this directory — mirroring
+                      the relative source structure — instead of
+                      overwriting the originals. Unchanged documents are
+                      copied as-is.
+        --precompress <gzip,brotli>
+                      Save gzip and/or brotli copies of each minified
+                      document alongside it, e.g. index.html.gz, unless
+                      they'd be larger than the document itself.
+        --report <json[=FILE]>
+                      Print a JSON report of the per-document results to
+                      STDOUT, or save it to FILE.
+        --suffix <SUFFIX>
+                      Save minified copies alongside the originals, adding
+                      this suffix before the extension, e.g. "min" turns
+                      foo.html into foo.min.html. Documents already bearing
+                      the suffix are skipped.
+        --top <NUM>   List the NUM documents with the most bytes saved, the
+                      highest percentage saved, and the largest output, as
+                      well as the largest ones left unchanged.
 
 ARGS:
     <PATH(S)>...      One or more files or directories to compress.
+
+EXIT CODES:
+    0                 Success.
+    1                 Fatal error, --check changes, or --strict skips.
+    2                 Some documents were skipped.
+
+CONFIGURATION:
+    Settings may also be placed in an htminl.toml file in the working
+    directory and/or any crawled directory, the latter overriding the
+    former for its own subtree:
+
+        ext = ["xhtml"]             # Extra extensions.
+        include = ["**/*.html"]     # Relative to the file.
+        exclude = ["vendor/**"]     # Relative to the file.
+        preset = "default"          # Or "safe" or "aggressive".
+        preserve-comments = true    # Keep <!-- comments -->.
+
+        [whitespace]
+        collapse = ["my-card"]      # Custom elements safe to collapse.
+        preserve = ["my-code"]      # Elements to leave untouched.
+
+        [[pass]]                    # Custom passes, run in order.
+        kind = "strip-attrs"
+        attrs = ["data-test"]
+
+        [[pass]]
+        kind = "rewrite-urls"       # action/href/poster/src/srcset.
+        from = "https://old.com/"
+        to = "https://new.com/"
 "#);
 
 
@@ -46,15 +172,39 @@ ARGS:
 #[derive(Debug, Clone)]
 /// # Generic Error.
 pub(super) enum HtminlError {
+	Backup(ErrorContext),
+	BackupDir,
+	CacheSave,
+	Config(String),
 	EmptyFile,
+	Extension,
+	Glob,
 	InvalidCli(String),
+	Jobs,
 	JobServer,
 	Killed,
 	ListFile,
+	MaxSize,
 	NoDocuments,
-	Parse,
-	Read,
-	Save,
+	OutDir,
+	OutputConflict,
+	Parse(ErrorContext),
+	Precompress,
+	Read(ErrorContext),
+	Report,
+	ReportSave,
+	Restore(ErrorContext),
+	RestoreConflict,
+	Save(ErrorContext),
+	Skipped(u64),
+	StdinPaths,
+	Strict(u64),
+	Suffix,
+	Top,
+	TooBig,
+	Unminified(u64),
+	Unrestored(u64),
+	Watch,
 	PrintHelp,    // Not an error.
 	PrintVersion, // Not an error.
 }
@@ -64,12 +214,40 @@ impl fmt::Display for HtminlError {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		let prefix = self.as_str();
 		match self {
-			Self::InvalidCli(s) => write!(
+			Self::Config(s) | Self::InvalidCli(s) => write!(
 				f,
 				concat!("{} ", dim!("{}")),
 				prefix,
 				s,
 			),
+			Self::Backup(c) | Self::Parse(c) | Self::Read(c) |
+			Self::Restore(c) | Self::Save(c) => write!(
+				f,
+				concat!("{} ", dim!("{}")),
+				prefix,
+				c,
+			),
+			Self::Skipped(n) => write!(
+				f,
+				"{} skipped.",
+				n.nice_inflect("document was", "documents were"),
+			),
+			Self::Strict(n) => write!(
+				f,
+				"{} skipped. {}",
+				n.nice_inflect("document was", "documents were"),
+				dim!("(--strict)"),
+			),
+			Self::Unminified(n) => write!(
+				f,
+				"{} would be changed by minification.",
+				n.nice_inflect("document", "documents"),
+			),
+			Self::Unrestored(n) => write!(
+				f,
+				"{} could not be restored.",
+				n.nice_inflect("backup", "backups"),
+			),
 			_ => f.write_str(prefix),
 		}
 	}
@@ -77,21 +255,209 @@ impl fmt::Display for HtminlError {
 
 impl Error for HtminlError {}
 
+impl From<MinifyError> for HtminlError {
+	fn from(err: MinifyError) -> Self {
+		let context = ErrorContext::new(err.as_str());
+		match err {
+			MinifyError::Encoding => Self::Read(context),
+			MinifyError::Parse(m) => Self::Parse(ErrorContext::new(m)),
+			MinifyError::Serialize => Self::Save(context),
+			MinifyError::Write(kind) => Self::Save(ErrorContext { io: Some(kind), ..context }),
+		}
+	}
+}
+
 impl HtminlError {
 	/// # As Str.
 	pub(super) const fn as_str(&self) -> &'static str {
 		match self {
+			Self::Backup(_) => "Unable to back up the original.",
+			Self::BackupDir => "Invalid/unwritable --backup directory.",
+			Self::CacheSave => "Unable to save the cache manifest.",
+			Self::Config(_) => "Invalid htminl.toml:",
 			Self::EmptyFile => "The file is empty.",
+			Self::Extension => "Invalid --ext extension.",
+			Self::Glob => "Invalid --include/--exclude pattern.",
 			Self::InvalidCli(_) => "Invalid/unknown argument:",
+			Self::Jobs => "Invalid -j/--jobs count.",
 			Self::JobServer => "One or more threads terminated early; please try again.",
 			Self::Killed => "The process was aborted early.",
 			Self::ListFile => "Invalid -l/--list text file.",
+			Self::MaxSize => "Invalid --max-file-size.",
 			Self::NoDocuments => "No documents were found.",
-			Self::Parse => "Unable to parse document.",
-			Self::Read => "Unable to read document.",
-			Self::Save => "Unable to save document.",
+			Self::OutDir => "Invalid --out-dir directory.",
+			Self::OutputConflict => "--out-dir and --suffix cannot be used together.",
+			Self::Parse(_) => "Unable to parse document.",
+			Self::Precompress => "Invalid --precompress encoding(s).",
+			Self::Read(_) => "Unable to read document.",
+			Self::Report => "Invalid --report format.",
+			Self::ReportSave => "Unable to save the report.",
+			Self::Restore(_) => "Unable to restore the backup.",
+			Self::RestoreConflict => "--restore cannot be combined with --check or --diff.",
+			Self::Save(_) => "Unable to save document.",
+			Self::Skipped(_) => "One or more documents were skipped.",
+			Self::StdinPaths => "STDIN/STDOUT mode cannot be combined with other paths.",
+			Self::Strict(_) => "One or more documents were skipped. (--strict)",
+			Self::Suffix => "Invalid --suffix.",
+			Self::Top => "Invalid --top count.",
+			Self::TooBig => "The file exceeds --max-file-size.",
+			Self::Unminified(_) => "One or more documents would be changed by minification.",
+			Self::Unrestored(_) => "One or more backups could not be restored.",
+			Self::Watch => "Unable to watch for changes. (Linux only, directories only, no STDIN.)",
 			Self::PrintHelp => HELP,
 			Self::PrintVersion => concat!("HTMinL v", env!("CARGO_PKG_VERSION")),
 		}
 	}
+
+	/// # Kind.
+	///
+	/// Return the variant name, e.g. for machine-readable reports.
+	pub(super) const fn kind(&self) -> &'static str {
+		match self {
+			Self::Backup(_) => "Backup",
+			Self::BackupDir => "BackupDir",
+			Self::CacheSave => "CacheSave",
+			Self::Config(_) => "Config",
+			Self::EmptyFile => "EmptyFile",
+			Self::Extension => "Extension",
+			Self::Glob => "Glob",
+			Self::InvalidCli(_) => "InvalidCli",
+			Self::Jobs => "Jobs",
+			Self::JobServer => "JobServer",
+			Self::Killed => "Killed",
+			Self::ListFile => "ListFile",
+			Self::MaxSize => "MaxSize",
+			Self::NoDocuments => "NoDocuments",
+			Self::OutDir => "OutDir",
+			Self::OutputConflict => "OutputConflict",
+			Self::Parse(_) => "Parse",
+			Self::Precompress => "Precompress",
+			Self::Read(_) => "Read",
+			Self::Report => "Report",
+			Self::ReportSave => "ReportSave",
+			Self::Restore(_) => "Restore",
+			Self::RestoreConflict => "RestoreConflict",
+			Self::Save(_) => "Save",
+			Self::Skipped(_) => "Skipped",
+			Self::StdinPaths => "StdinPaths",
+			Self::Strict(_) => "Strict",
+			Self::Suffix => "Suffix",
+			Self::Top => "Top",
+			Self::TooBig => "TooBig",
+			Self::Unminified(_) => "Unminified",
+			Self::Unrestored(_) => "Unrestored",
+			Self::Watch => "Watch",
+			Self::PrintHelp => "PrintHelp",
+			Self::PrintVersion => "PrintVersion",
+		}
+	}
+
+	#[must_use]
+	/// # Context.
+	///
+	/// Return the error context, if any.
+	pub(super) const fn context(&self) -> Option<&ErrorContext> {
+		match self {
+			Self::Backup(c) | Self::Parse(c) | Self::Read(c) |
+			Self::Restore(c) | Self::Save(c) => Some(c),
+			_ => None,
+		}
+	}
+
+	#[must_use]
+	/// # With Path.
+	///
+	/// Attach `path` to the error context, if any, unless it already has one.
+	pub(super) fn with_path(self, path: &Path) -> Self {
+		match self {
+			Self::Backup(c) => Self::Backup(c.with_path(path)),
+			Self::Parse(c) => Self::Parse(c.with_path(path)),
+			Self::Read(c) => Self::Read(c.with_path(path)),
+			Self::Restore(c) => Self::Restore(c.with_path(path)),
+			Self::Save(c) => Self::Save(c.with_path(path)),
+			e => e,
+		}
+	}
+
+	#[must_use]
+	/// # Reason.
+	///
+	/// Return the general description of the error along with the specific
+	/// message from its context, if any, e.g. for skip notices.
+	///
+	/// The context path is omitted if it matches `src`, since that'll usually
+	/// be printed separately.
+	pub(super) fn reason(&self, src: &Path) -> Cow<'static, str> {
+		let prefix = self.as_str();
+		match self.context() {
+			Some(c) if c.path.as_deref().is_none_or(|p| p == src) =>
+				Cow::Owned(format!("{prefix} {}", c.message)),
+			Some(c) => Cow::Owned(format!("{prefix} {c}")),
+			None => Cow::Borrowed(prefix),
+		}
+	}
+}
+
+
+
+#[derive(Debug, Clone, Default)]
+/// # Error Context.
+///
+/// This holds the finer details of document-level errors: the path involved,
+/// the underlying I/O error kind (if any), and a short description of what
+/// went wrong.
+pub(super) struct ErrorContext {
+	/// # Path.
+	path: Option<PathBuf>,
+
+	/// # I/O Error Kind.
+	io: Option<io::ErrorKind>,
+
+	/// # Message.
+	message: Cow<'static, str>,
+}
+
+impl fmt::Display for ErrorContext {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if let Some(path) = &self.path { write!(f, "{}: ", path.display())?; }
+		f.write_str(&self.message)
+	}
+}
+
+impl ErrorContext {
+	#[must_use]
+	/// # New.
+	///
+	/// Return a context with a static message and no path, e.g. for parser
+	/// failures.
+	pub(super) const fn new(message: &'static str) -> Self {
+		Self {
+			path: None,
+			io: None,
+			message: Cow::Borrowed(message),
+		}
+	}
+
+	#[must_use]
+	/// # From I/O Error.
+	pub(super) fn io(path: &Path, err: &io::Error) -> Self {
+		Self {
+			path: Some(path.to_path_buf()),
+			io: Some(err.kind()),
+			message: Cow::Owned(err.to_string()),
+		}
+	}
+
+	#[must_use]
+	/// # With Path.
+	///
+	/// Set the path, unless one was already set.
+	pub(super) fn with_path(mut self, path: &Path) -> Self {
+		if self.path.is_none() { self.path = Some(path.to_path_buf()); }
+		self
+	}
+
+	#[must_use]
+	/// # I/O Error Kind.
+	pub(super) const fn io_kind(&self) -> Option<io::ErrorKind> { self.io }
 }
@@ -0,0 +1,95 @@
+/*!
+# HTMinL: Fragments.
+*/
+
+
+
+/// # Fragment Tag Name.
+pub(super) const TAG: &str = "fragment-marker";
+
+/// # Fragment Open.
+const FRAGMENT_OPEN: &str = "<fragment-marker>";
+
+/// # Fragment Close.
+const FRAGMENT_CLOSE: &str = "</fragment-marker>";
+
+
+
+/// # Is Fragment.
+///
+/// This returns `false` if the document contains (case-insensitively)
+/// `<html`, `<body`, `</body>`, or `</html>`.
+pub(super) fn is_fragment(src: &[u8]) -> bool {
+	for w in src.array_windows::<7>() {
+		if w[0] == b'<' {
+			match w[1] {
+				b'/' if w[6] == b'>' => {
+					let mid = &w[2..6];
+					if mid.eq_ignore_ascii_case(b"body") || mid.eq_ignore_ascii_case(b"html") {
+						return false;
+					}
+				},
+				b'b' | b'B' if w[2..5].eq_ignore_ascii_case(b"ody") => { return false; },
+				b'h' | b'H' if w[2..5].eq_ignore_ascii_case(b"tml") => { return false; },
+				_ => {},
+			}
+		}
+	}
+
+	true
+}
+
+/// # Make (Whole) Fragment.
+///
+/// The content is assumed to have been a "fragment" to begin with, but will
+/// only be altered — turned back into said fragment — if both the opening and
+/// closing markers are present.
+///
+/// Returns `false` if either marker is missing, indicating corruption.
+pub(super) fn make_fragment(src: &mut String) -> bool {
+	if
+		let Some(open) = src.find(FRAGMENT_OPEN) &&
+		let Some(close) = src.rfind(FRAGMENT_CLOSE)
+	{
+		src.truncate(close);
+		src.replace_range(..open + FRAGMENT_OPEN.len(), "");
+		true
+	}
+	else { false }
+}
+
+/// # Make (Fragment) Whole.
+///
+/// Wrap fragmentary HTML in special marker tags so we can find the relevant
+/// part again after processing.
+pub(super) fn make_whole(src: &mut String) {
+	src.insert_str(0, FRAGMENT_OPEN);
+	src.push_str(FRAGMENT_CLOSE);
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn t_fragment() {
+		assert!(
+			! is_fragment(include_bytes!("../skel/test-assets/blobfolio.com.html"))
+		);
+
+		let frag = include_str!("../skel/test-assets/fragment.html");
+		assert!(is_fragment(frag.as_bytes()));
+
+		// Now make it whole.
+		let mut frag2 = frag.to_owned();
+		make_whole(&mut frag2);
+		assert_ne!(frag, frag2); // Should be different now.
+
+		// Now make it a fragment again.
+		assert!(make_fragment(&mut frag2));
+		assert!(is_fragment(frag2.as_bytes()));
+		assert_eq!(frag, frag2);
+	}
+}
@@ -0,0 +1,457 @@
+/*!
+# HTMinL: Library
+
+This crate exposes the HTML minification behind the `htminl` CLI for use in
+other Rust programs, e.g. static site generators.
+
+The CLI and its (many) dependencies are gated behind the default `bin`
+feature, so library users should disable default features:
+
+```toml
+[dependencies.htminl]
+version = "0.11.*"
+default-features = false
+```
+
+## Examples
+
+```
+let html = "<p>  Hello   <b>World</b>! </p>";
+assert_eq!(
+    htminl::minify_str(html).unwrap(),
+    "<p> Hello <b>World</b>! </p>",
+);
+```
+*/
+
+#![forbid(unsafe_code)]
+
+#![deny(
+	clippy::allow_attributes_without_reason,
+	clippy::correctness,
+	unreachable_pub,
+)]
+
+#![warn(
+	clippy::complexity,
+	clippy::nursery,
+	clippy::pedantic,
+	clippy::perf,
+	clippy::style,
+
+	clippy::allow_attributes,
+	clippy::clone_on_ref_ptr,
+	clippy::create_dir,
+	clippy::filetype_is_file,
+	clippy::format_push_string,
+	clippy::get_unwrap,
+	clippy::impl_trait_in_params,
+	clippy::implicit_clone,
+	clippy::lossy_float_literal,
+	clippy::missing_assert_message,
+	clippy::missing_docs_in_private_items,
+	clippy::needless_raw_strings,
+	clippy::panic_in_result_fn,
+	clippy::pub_without_shorthand,
+	clippy::rest_pat_in_fully_bound_structs,
+	clippy::semicolon_inside_block,
+	clippy::str_to_string,
+	clippy::todo,
+	clippy::undocumented_unsafe_blocks,
+	clippy::unneeded_field_pattern,
+	clippy::unseparated_literal_suffix,
+	clippy::unwrap_in_result,
+
+	macro_use_extern_crate,
+	missing_copy_implementations,
+	missing_docs,
+	non_ascii_idents,
+	trivial_casts,
+	trivial_numeric_casts,
+	unused_extern_crates,
+	unused_import_braces,
+)]
+
+#![expect(clippy::redundant_pub_crate, reason = "Unresolvable.")]
+#![expect(clippy::doc_markdown, reason = "HTMinL makes this annoying.")]
+
+mod dom;
+mod fragment;
+
+use dom::node::{
+	Handle,
+	Node,
+	NodeInner,
+};
+pub use dom::{
+	options::MinifyOptions,
+	pass::{
+		Element,
+		Pass,
+		Stage,
+		Text,
+	},
+	savings::{
+		Category,
+		Savings,
+	},
+	timings::{
+		Phase,
+		Timings,
+	},
+};
+use dom::Tree;
+use std::{
+	error::Error,
+	fmt,
+	io,
+};
+
+
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+/// # Minification Error.
+pub enum MinifyError {
+	/// # Invalid UTF-8.
+	Encoding,
+
+	/// # Parse Failure.
+	///
+	/// This includes a short description of what went wrong.
+	Parse(&'static str),
+
+	/// # Serialization Failure.
+	Serialize,
+
+	/// # Write Failure.
+	///
+	/// The writer passed to `minify_to` returned an error of this kind.
+	Write(io::ErrorKind),
+}
+
+impl Error for MinifyError {}
+
+impl fmt::Display for MinifyError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Parse(m) => write!(f, "{} ({m})", self.as_str()),
+			Self::Write(kind) => write!(f, "{} ({kind})", self.as_str()),
+			_ => f.write_str(self.as_str()),
+		}
+	}
+}
+
+impl MinifyError {
+	#[must_use]
+	/// # As Str.
+	///
+	/// Return the general description of the error, sans context.
+	pub const fn as_str(&self) -> &'static str {
+		match self {
+			Self::Encoding => "The document is not valid UTF-8.",
+			Self::Parse(_) => "Unable to parse document.",
+			Self::Serialize => "The tree could not be serialized.",
+			Self::Write(_) => "Unable to write the output.",
+		}
+	}
+}
+
+
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// # Minification Details.
+///
+/// This is returned by `minify_html`, and holds the output along with a few
+/// other details about the process.
+pub struct Minification {
+	/// # Output.
+	///
+	/// This is only set if the output differs from the original.
+	pub html: Option<String>,
+
+	/// # Fragment?
+	///
+	/// True if the source lacked `<html>`/`<body>` tags, and was thus
+	/// minified as a fragment.
+	pub fragment: bool,
+
+	/// # Savings Breakdown.
+	///
+	/// This is only populated if the output differs from the original.
+	pub savings: Savings,
+
+	/// # Phase Timings.
+	///
+	/// This covers parsing through serialization.
+	pub timings: Timings,
+}
+
+
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+/// # Streamed Minification Details.
+///
+/// This is returned by `minify_to`, and holds a few details about the
+/// process. The output itself went to the writer.
+pub struct Streamed {
+	/// # Bytes Written.
+	pub written: u64,
+
+	/// # Fragment?
+	///
+	/// True if the source lacked `<html>`/`<body>` tags, and was thus
+	/// minified as a fragment.
+	pub fragment: bool,
+
+	/// # Savings Breakdown.
+	pub savings: Savings,
+
+	/// # Phase Timings.
+	///
+	/// This covers parsing through serialization.
+	pub timings: Timings,
+}
+
+
+
+/// # Minify HTML.
+///
+/// Normalize the line endings, parse the document into a tree, clean and
+/// minify said tree, and turn it _back_ into HTML, returning the result if it
+/// differs from the original, along with a few other details.
+///
+/// Documents lacking `<html>`/`<body>` tags are treated as fragments, and
+/// kept that way.
+///
+/// Note: line endings in `raw` are normalized in-place.
+///
+/// ## Errors
+///
+/// This will return an error if the document cannot be parsed or serialized.
+pub fn minify_html(raw: &mut String, opts: &MinifyOptions)
+-> Result<Minification, MinifyError> {
+	let size_hint = raw.len();
+
+	// Replace all CRLF/CR instances with LF before parsing anything.
+	let changed = normalize_line_endings(raw);
+
+	// If this is a "fragment", wrap it so we can tease the relevant bit back
+	// out after processing.
+	let fragment = fragment::is_fragment(raw.as_bytes());
+	if fragment { fragment::make_whole(raw); }
+
+	// Parse the document into a tree.
+	let dom = Tree::parse(raw.as_bytes(), opts)?;
+
+	// Turn it back into a string.
+	let mut out = dom.serialize(Some(size_hint))?;
+
+	// If the original was a fragment, re-fragmentize it.
+	if fragment {
+		fragment::make_fragment(raw); // Convert the original back too.
+		if ! fragment::make_fragment(&mut out) {
+			return Err(MinifyError::Parse(
+				"The fragment markers went missing during minification."
+			));
+		}
+	}
+
+	// Return it if different!
+	let timings = dom.timings();
+	if (changed || *raw != out) && ! out.is_empty() {
+		Ok(Minification { html: Some(out), fragment, savings: dom.savings(), timings })
+	}
+	else {
+		Ok(Minification { html: None, fragment, savings: Savings::default(), timings })
+	}
+}
+
+/// # Minify HTML (Streaming).
+///
+/// Same as `minify_html`, except the output is written — buffered — straight
+/// into `writer` instead of being returned as a `String`, making it possible
+/// to pipe documents directly into compressors, sockets, HTTP bodies, etc.
+///
+/// Unlike `minify_html`, the output is _always_ written, even if it happens
+/// to be identical to the original.
+///
+/// Note: line endings in `raw` are normalized in-place.
+///
+/// ## Examples
+///
+/// ```
+/// use htminl::MinifyOptions;
+///
+/// let mut raw = String::from("<p>  Hello  </p>");
+/// let mut out = Vec::new();
+/// let res = htminl::minify_to(&mut raw, &MinifyOptions::default(), &mut out)
+///     .unwrap();
+///
+/// assert_eq!(out, b"<p> Hello </p>");
+/// assert_eq!(res.written, 14);
+/// ```
+///
+/// ## Errors
+///
+/// This will return an error if the document cannot be parsed or serialized,
+/// or the writer fails. In the latter case, partial output may have already
+/// been written.
+pub fn minify_to<W: io::Write>(raw: &mut String, opts: &MinifyOptions, writer: W)
+-> Result<Streamed, MinifyError> {
+	normalize_line_endings(raw);
+
+	// Fragments are wrapped for parsing, but only the relevant bit is
+	// written back out.
+	let fragment = fragment::is_fragment(raw.as_bytes());
+	if fragment { fragment::make_whole(raw); }
+	let dom = Tree::parse(raw.as_bytes(), opts);
+	if fragment { fragment::make_fragment(raw); }
+	let dom = dom?;
+
+	let written = dom.serialize_into(writer, fragment)?;
+	Ok(Streamed {
+		written,
+		fragment,
+		savings: dom.savings(),
+		timings: dom.timings(),
+	})
+}
+
+/// # Minify String.
+///
+/// Minify an HTML document (or fragment) using the default options,
+/// returning the result. If no changes were needed, the result will simply
+/// be a copy of the original.
+///
+/// See `minify_html` for more control.
+///
+/// ## Examples
+///
+/// ```
+/// assert_eq!(
+///     htminl::minify_str("<div>\r\n  <p>Hello</p>\r\n</div>").unwrap(),
+///     "<div> <p>Hello</p> </div>",
+/// );
+/// ```
+///
+/// ## Errors
+///
+/// This will return an error if the document cannot be parsed or serialized.
+pub fn minify_str(src: &str) -> Result<String, MinifyError> {
+	let mut raw = src.to_owned();
+	let out = minify_html(&mut raw, &MinifyOptions::default())?;
+	Ok(out.html.unwrap_or(raw))
+}
+
+/// # Minify Bytes.
+///
+/// Same as `minify_str`, but for raw bytes, which must be valid UTF-8.
+///
+/// ## Examples
+///
+/// ```
+/// assert_eq!(
+///     htminl::minify_bytes(b"<p>  Hello  </p>").unwrap(),
+///     b"<p> Hello </p>",
+/// );
+/// ```
+///
+/// ## Errors
+///
+/// This will return an error if the document is not valid UTF-8, or cannot
+/// be parsed or serialized.
+pub fn minify_bytes(src: &[u8]) -> Result<Vec<u8>, MinifyError> {
+	let src = std::str::from_utf8(src).map_err(|_| MinifyError::Encoding)?;
+	minify_str(src).map(String::into_bytes)
+}
+
+
+
+/// # Normalize Line Endings.
+///
+/// Replace all CRLF/CR instances with LF, returning `true` if any were found.
+fn normalize_line_endings(raw: &mut String) -> bool {
+	let mut changed = false;
+	while let Some(pos) = raw.find("\r\n") {
+		raw.replace_range(pos..pos + 2, "\n");
+		changed = true;
+	}
+	while let Some(pos) = raw.find('\r') {
+		raw.replace_range(pos..=pos, "\n");
+		changed = true;
+	}
+	changed
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn t_minify_str() {
+		// Fragments stay fragments.
+		assert_eq!(
+			minify_str("<p>  Hello  <!-- Nope --></p>").as_deref(),
+			Ok("<p> Hello </p>"),
+		);
+
+		// Documents get a doctype.
+		assert_eq!(
+			minify_str("<html><body>  <p>Hi</p>  </body></html>").as_deref(),
+			Ok("<!DOCTYPE html>\n<html>\n<head></head>\n<body>\n<p>Hi</p>\n</body>\n</html>"),
+		);
+
+		// Already minified.
+		assert_eq!(minify_str("<p>Hi</p>").as_deref(), Ok("<p>Hi</p>"));
+		assert_eq!(minify_str("").as_deref(), Ok(""));
+
+		// Bytes should work the same way, provided they're UTF-8.
+		assert_eq!(minify_bytes(b"<p>  Hello  </p>").as_deref(), Ok(&b"<p> Hello </p>"[..]));
+		assert_eq!(minify_bytes(b"<p>\xFF</p>"), Err(MinifyError::Encoding));
+	}
+
+	#[test]
+	fn t_minify_html() {
+		let mut raw = String::from("<p>\r\n  Hello  \r\n</p>");
+		let opts = MinifyOptions::default();
+		let out = minify_html(&mut raw, &opts).expect("Minification failed.");
+		assert_eq!(raw, "<p>\n  Hello  \n</p>", "Line endings should be normalized.");
+		assert_eq!(out.html.as_deref(), Some("<p> Hello </p>"));
+		assert!(out.fragment);
+		assert_eq!(out.savings.get(Category::Whitespace), 4);
+
+		// No changes, no output.
+		let mut raw = String::from("<p>Hello</p>");
+		let out = minify_html(&mut raw, &opts).expect("Minification failed.");
+		assert_eq!(out.html, None);
+		assert_eq!(out.savings, Savings::default());
+	}
+
+	#[test]
+	fn t_minify_to() {
+		// The streamed output should match the buffered version exactly.
+		for src in [
+			include_str!("../skel/test-assets/blobfolio.com.html"),
+			include_str!("../skel/test-assets/fragment.html"),
+			"<p>\r\n  Hello  \r\n</p>",
+		] {
+			for opts in [MinifyOptions::default(), MinifyOptions::aggressive()] {
+				let mut raw = src.to_owned();
+				let mut out = Vec::new();
+				let res = minify_to(&mut raw, &opts, &mut out).expect("Minification failed.");
+				assert_eq!(res.written, out.len() as u64);
+
+				let mut raw2 = src.to_owned();
+				let expected = minify_html(&mut raw2, &opts).expect("Minification failed.");
+				assert_eq!(raw, raw2, "The source should be left the same way.");
+				assert_eq!(res.fragment, expected.fragment);
+				assert_eq!(
+					std::str::from_utf8(&out).ok(),
+					Some(expected.html.as_deref().unwrap_or(&raw2)),
+				);
+			}
+		}
+	}
+}
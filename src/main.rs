@@ -54,38 +54,77 @@
 #![expect(clippy::redundant_pub_crate, reason = "Unresolvable.")]
 #![expect(clippy::doc_markdown, reason = "HTMinL makes this annoying.")]
 
-mod dom;
+mod backup;
+mod cache;
+mod config;
+mod diff;
+mod discover;
 mod err;
+mod meta;
 mod minify;
+mod output;
+mod passes;
+mod precompress;
+mod profile;
+mod report;
+mod settings;
+mod top;
+mod watch;
 
 use dactyl::{
 	NiceElapsed,
+	NicePercent,
 	NiceU64,
 	traits::NiceInflection,
 };
-use dom::{
-	node::{
-		Handle,
-		Node,
-		NodeInner,
-	},
-	Tree,
-};
+use backup::Backup;
+use cache::Cache;
+use config::Configs;
+use diff::Diff;
+use meta::Preserve;
+use discover::Discovery;
 use dowser::{
 	Dowser,
 	Extension,
 };
-use err::HtminlError;
+use err::{
+	ErrorContext,
+	HtminlError,
+};
 use flume::Receiver;
+use minify::Minified;
+use output::{
+	Mirror,
+	Output,
+};
+use precompress::Precompress;
+use report::{
+	Record,
+	Report,
+};
+use settings::Settings;
+use watch::Written;
 use fyi_msg::{
 	fyi_ansi::dim,
 	BeforeAfter,
 	Msg,
-	MsgKind,
 	Progless,
 };
+use html5ever as _; // Library-only.
+use indexmap as _;  // Library-only.
+use htminl::{
+	Category,
+	MinifyOptions,
+	Phase,
+	Savings,
+	Timings,
+};
 use std::{
-	num::NonZeroUsize,
+	borrow::Cow,
+	num::{
+		NonZeroU64,
+		NonZeroUsize,
+	},
 	path::{
 		Path,
 		PathBuf,
@@ -96,14 +135,29 @@ use std::{
 		Ordering::SeqCst,
 	},
 	thread,
+	time::{
+		Duration,
+		Instant,
+	},
 };
 
+/// # Exit Code: Some Documents Skipped.
+///
+/// Fatal errors use the generic `1`.
+const EXIT_SKIPPED: u8 = 2;
+
 /// # Extension: HTM.
 const E_HTM: Extension = Extension::new("htm").unwrap();
 
 /// # Extension: HTML.
 const E_HTML: Extension = Extension::new("html").unwrap();
 
+/// # Cached Count.
+static CACHED: AtomicU64 = AtomicU64::new(0);
+
+/// # Changed Count (Check Mode).
+static CHANGED: AtomicU64 = AtomicU64::new(0);
+
 /// # Skip Count.
 static SKIPPED: AtomicU64 = AtomicU64::new(0);
 
@@ -113,6 +167,9 @@ static BEFORE: AtomicU64 = AtomicU64::new(0);
 /// # Total Size After.
 static AFTER: AtomicU64 = AtomicU64::new(0);
 
+/// # Total Savings (By Category).
+static SAVINGS: [AtomicU64; Category::COUNT] = [const { AtomicU64::new(0) }; Category::COUNT];
+
 
 
 /// # Main.
@@ -123,8 +180,12 @@ fn main() -> ExitCode {
 			println!("{e}");
 			ExitCode::SUCCESS
 		},
+		Err(e @ HtminlError::Skipped(_)) => {
+			eprint(Msg::warning(e.to_string()));
+			ExitCode::from(EXIT_SKIPPED)
+		},
 		Err(e) => {
-			Msg::error(e.to_string()).eprint();
+			eprint(Msg::error(e.to_string()));
 			ExitCode::FAILURE
 		},
 	}
@@ -133,97 +194,181 @@ fn main() -> ExitCode {
 #[inline]
 /// # Actual Main.
 fn main__() -> Result<(), HtminlError> {
-	argyle::argue! {
-		Help     "-h" "--help",
-		Progress "-p" "--progress",
-		Version  "-V" "--version",
-
-		@options
-		List     "-l" "--list",
-
-		@catchall-paths Path,
+	let settings = Settings::new()?;
+
+	// Minify STDIN to STDOUT instead?
+	if settings.stdin {
+		let check = settings.output.is_dry_run();
+		let opts = settings.configs.as_ref()
+			.map(|c| c.resolve(None).options())
+			.unwrap_or_default();
+		return match minify::minify_stdin(&settings.output, settings.max_size, &opts) {
+			Ok(true) if check => Err(HtminlError::Unminified(1)),
+			Ok(_) => Ok(()),
+			Err(e) => Err(e),
+		};
 	}
 
-	// Parse CLI arguments.
-	let mut progress = false;
+	// Put it all together!
+	let Settings {
+		backup, breakdown, cache, configs, mut discovery, entries, jobs, max_size,
+		output, precompress, preserve, profile, progress, report, restore, strict,
+		top, watch, ..
+	} = settings;
+	let now = Instant::now();
+	let is_ours = |p: &Path|
+		output.is_dst(p) ||
+		backup.as_ref().is_some_and(|b| b.is_backup(p));
 	let mut paths = Dowser::default();
-	for arg in Argument::args_os() {
-		match arg {
-			Argument::Help =>     return Err(HtminlError::PrintHelp),
-			Argument::Progress => { progress = true; },
-			Argument::Version =>  return Err(HtminlError::PrintVersion),
-
-			Argument::List(s) =>
-				if s == "-" { paths.push_paths_from_stdin(); }
-				else {
-					paths.push_paths_from_file(&s).map_err(|_| HtminlError::ListFile)?;
-				},
-
-			Argument::Path(s) => { paths = paths.with_path(s); },
-
-			// Mistake?
-			Argument::Other(s) =>   return Err(HtminlError::InvalidCli(s)),
-			Argument::OtherOs(s) => return Err(HtminlError::InvalidCli(s.to_string_lossy().into_owned())),
-		}
+	if let Some(dir) = output.out_dir() { paths = paths.without_path(dir); }
+	if let Some(dir) = backup.as_ref().and_then(Backup::backup_dir) {
+		paths = paths.without_path(dir);
 	}
-
-	// Put it all together!
-	let mut paths: Vec<PathBuf> = paths.filter(|p|
-		matches!(Extension::from_path(p), Some(E_HTM | E_HTML))
-	)
+	let mut paths: Vec<PathBuf> = entries.iter()
+		.fold(paths, Dowser::with_path)
+		.filter(|p| ! is_ours(p))
 		.collect();
+	discovery.retain(&mut paths);
 	let total = NonZeroUsize::new(paths.len()).ok_or(HtminlError::NoDocuments)?;
 	paths.sort();
 
+	// Restore the backups instead?
+	if restore && let Some(backup) = backup {
+		return restore_all(&backup, &paths);
+	}
+
 	// How many threads?
-	let threads = thread::available_parallelism().map_or(
-		NonZeroUsize::MIN,
-		|t| NonZeroUsize::min(t, total),
-	);
+	let threads = jobs.or_else(|| thread::available_parallelism().ok())
+		.unwrap_or(NonZeroUsize::MIN)
+		.min(total);
 
 	// Set up the killswitch.
 	let killed = Progless::sigint_two_strike();
 
 	// Boot up a progress bar, if desired.
 	let progress =
-		if progress {
+		if progress && ! watch {
 			Progless::try_from(total)
 				.ok()
 				.map(|p| p.with_reticulating_splines("HTMinL"))
 		}
 		else { None };
 
+	// Shared worker settings.
+	let cache = cache.map(Cache::load);
+	let written = watch.then(Written::default);
+	let job = Crunch {
+		progress: progress.as_ref(),
+		output: &output,
+		max_size,
+		precompress,
+		preserve,
+		backup: backup.as_ref(),
+		cache: cache.as_ref(),
+		configs: configs.as_deref(),
+		written: written.as_ref(),
+		records: report.is_some() || top.is_some() || profile,
+	};
+
 	// Thread business!
-	let (tx, rx) = flume::bounded::<&Path>(threads.get());
-	thread::scope(#[inline(always)] |s| {
+	let (tx, rx) = flume::bounded::<Cow<Path>>(threads.get());
+	let mut records = thread::scope(#[inline(always)] |s| {
 		// Set up the worker threads.
 		let mut workers = Vec::with_capacity(threads.get());
 		for _ in 0..threads.get() {
 			let rx2 = rx.clone();
-			workers.push(s.spawn(#[inline(always)] || crunch(rx2, progress.as_ref())));
+			workers.push(s.spawn(#[inline(always)] || crunch(rx2, &job)));
 		}
 		drop(rx);
 
-		// Push all the files to it, then drop the sender to disconnect.
+		// Push all the files to it.
 		for path in &paths {
-			if killed.load(SeqCst) || tx.send(path).is_err() { break; }
+			if killed.load(SeqCst) || tx.send(Cow::Borrowed(path)).is_err() { break; }
 		}
+
+		// Keep pushing files as they change?
+		let watched = written.as_ref().map_or(Ok(()), |written| watch::watch(
+			&entries,
+			output.out_dir(),
+			written,
+			killed,
+			|p| ! is_ours(p) && discovery.matches(p),
+			&tx,
+		));
+
+		// Drop the sender to disconnect.
 		drop(tx);
 
-		// Sum the totals as each thread finishes.
+		// Collect the records as each thread finishes.
+		let mut records = Vec::new();
 		for worker in workers {
-			worker.join().map_err(|_| HtminlError::JobServer)?;
+			records.append(&mut worker.join().map_err(|_| HtminlError::JobServer)?);
 		}
 
-		Ok::<(), HtminlError>(())
+		watched.map(|()| records)
 	})?;
 
-	// Summarize?
-	if let Some(progress) = progress { summarize(&progress, total.get() as u64); }
+	// Summarize.
+	if ! watch {
+		let elapsed = progress.as_ref().map_or_else(|| now.elapsed(), Progless::finish);
+		summarize(elapsed, total.get() as u64, output.is_dry_run(), breakdown, top, profile, &records);
+	}
+
+	// Report?
+	if let Some(report) = report { report.write(&mut records)?; }
+
+	// Save the cache?
+	if let Some(cache) = cache { cache.save()?; }
+
+	// Early abort? (That's the only way out of watch mode.)
+	if killed.load(SeqCst) && ! watch { return Err(HtminlError::Killed); }
+
+	// Check failure?
+	let changed = CHANGED.load(SeqCst);
+	if changed != 0 { return Err(HtminlError::Unminified(changed)); }
+
+	// Skips?
+	let skipped = SKIPPED.load(SeqCst);
+	if skipped == 0 { Ok(()) }
+	else if strict { Err(HtminlError::Strict(skipped)) }
+	else { Err(HtminlError::Skipped(skipped)) }
+}
+
+/// # Worker Settings.
+///
+/// This holds the settings shared by all of the `crunch` workers.
+struct Crunch<'a> {
+	/// # Progress Bar.
+	progress: Option<&'a Progless>,
+
+	/// # Output Destination.
+	output: &'a Output,
+
+	/// # Maximum File Size.
+	max_size: Option<NonZeroU64>,
 
-	// Early abort?
-	if killed.load(SeqCst) { Err(HtminlError::Killed) }
-	else { Ok(()) }
+	/// # Precompression.
+	precompress: Option<Precompress>,
+
+	/// # Metadata Preservation.
+	preserve: Preserve,
+
+	/// # Backups.
+	backup: Option<&'a Backup>,
+
+	/// # Incremental Cache.
+	cache: Option<&'a Cache>,
+
+	/// # Configuration Files.
+	configs: Option<&'a Configs>,
+
+	/// # Written Files (Watch Mode).
+	written: Option<&'a Written>,
+
+	/// # Collect Records?
+	///
+	/// These are needed for reports, `--top` rankings, and profiling.
+	records: bool,
 }
 
 #[expect(clippy::needless_pass_by_value, reason = "For drop.")]
@@ -233,55 +378,264 @@ fn main__() -> Result<(), HtminlError> {
 /// This is the worker callback for HTML crunching. It listens for "new" HTML
 /// paths and crunches them — and maybe updates the progress bar, etc. — then
 /// quits as soon as the work has dried up.
-fn crunch(rx: Receiver::<&Path>, progress: Option<&Progless>) {
-	let Some(progress) = progress else {
-		// If we aren't tracking progress, the code is a lot simpler. Haha.
-		while let Ok(p) = rx.recv() { let _res = minify::minify(p); }
-		return;
-	};
-
-	// The pretty version.
+///
+/// For dry runs, documents are left as-is, but any that _would_ have been
+/// changed are reported.
+///
+/// If precompression is enabled, gzip and/or brotli copies of each saved
+/// document are generated alongside it.
+///
+/// If there is a cache, documents unchanged since the last run are skipped,
+/// and the rest recorded for next time.
+///
+/// In watch mode, the files written are noted so the watcher can ignore
+/// them, and each changed or skipped document is reported as it happens.
+///
+/// If reporting or ranking, the per-document results are collected and
+/// returned once finished.
+fn crunch<'a>(rx: Receiver::<Cow<'a, Path>>, job: &Crunch<'_>) -> Vec<Record<'a>> {
+	let check = job.output.is_dry_run();
+	let mut records = Vec::new();
 	while let Ok(p) = rx.recv() {
-		match minify::minify(p) {
-			Ok((b, a)) => {
-				BEFORE.fetch_add(b.get(), SeqCst);
-				AFTER.fetch_add(a.get(), SeqCst);
+		let now = Instant::now();
+
+		// Skip documents that haven't changed since the last run.
+		let resolved = job.configs.map(|c| c.resolve(Some(&p))).unwrap_or_default();
+		let opts = resolved.options();
+		let config = resolved.fingerprint(&opts, job.precompress, job.preserve);
+		if let Some(m) = job.cache.and_then(|c| c.check(&p, job.output, config)) {
+			CACHED.fetch_add(1, SeqCst);
+			BEFORE.fetch_add(m.before.get(), SeqCst);
+			AFTER.fetch_add(m.before.get(), SeqCst);
+			if job.records {
+				records.push(Record { path: p, result: Ok(m), cached: true, elapsed: now.elapsed() });
+			}
+			continue;
+		}
+
+		let mut res = minify::minify(
+			&p,
+			job.output,
+			job.max_size,
+			&opts,
+			job.preserve,
+			job.backup,
+		);
+		let elapsed = now.elapsed();
+
+		// Note what we wrote — ASAP — so the watcher can ignore it. (The
+		// output directory isn't watched.) Then precompress it, if desired;
+		// failures there count as skips too.
+		let mut siblings = Precompress::default();
+		if let Ok(m) = &res && let Some(dst) = job.output.dst(&p) {
+			if
+				let Some(written) = job.written &&
+				(m.after.is_some() || dst != p) &&
+				job.output.out_dir().is_none()
+			{
+				written.add(&dst);
+			}
+			if let Some(precompress) = job.precompress {
+				match precompress.encode(&dst) {
+					Ok(s) => { siblings = s; },
+					Err(e) => { res = Err(e); },
+				}
+			}
+		}
+
+		match res {
+			Ok(Minified { before, after, savings, .. }) => {
+				BEFORE.fetch_add(before.get(), SeqCst);
+				AFTER.fetch_add(after.unwrap_or(before).get(), SeqCst);
+				for (kind, total) in Category::ALL.into_iter().zip(&SAVINGS) {
+					total.fetch_add(savings.get(kind), SeqCst);
+				}
+				if job.written.is_some() && ! check && let Some(after) = after {
+					eprint(Msg::crunched(sizes(&p, before, after)));
+				}
+				if check && let Some(after) = after {
+					CHANGED.fetch_add(1, SeqCst);
+					let msg = Msg::review(sizes(&p, before, after));
+					if let Some(progress) = job.progress { let _res = progress.push_msg(msg); }
+					else { eprint(msg); }
+				}
 			},
-			Err(e) => {
+			Err(ref e) => {
 				SKIPPED.fetch_add(1, SeqCst);
-				let _res = progress.push_msg(Msg::skipped(format!(
-					concat!("{} ", dim!("({})")),
-					p.display(),
-					e.as_str(),
-				)));
+				let msg = skipped(&p, e);
+				if let Some(progress) = job.progress { let _res = progress.push_msg(msg); }
+				else { eprint(msg); }
 			}
 		}
+
+		if let Some(cache) = job.cache {
+			cache.update(&p, job.output, config, siblings, res.as_ref().ok());
+		}
+		if job.records { records.push(Record { path: p, result: res, cached: false, elapsed }); }
+	}
+
+	records
+}
+
+/// # Restore Backups.
+///
+/// Move the backups of each of the `paths`, if any, back into place,
+/// summarizing the results at the end.
+///
+/// ## Errors
+///
+/// This will return an error if any of the backups could not be restored.
+fn restore_all(backup: &Backup, paths: &[PathBuf]) -> Result<(), HtminlError> {
+	let mut restored = 0_u64;
+	let mut failed = 0_u64;
+	for p in paths {
+		match backup.restore(p) {
+			Ok(true) => { restored += 1; },
+			Ok(false) => {},
+			Err(e) => {
+				failed += 1;
+				eprint(skipped(p, &e));
+			},
+		}
+	}
+
+	eprint(Msg::info(format!(
+		"Restored {}.",
+		restored.nice_inflect("document", "documents"),
+	)));
+
+	if failed == 0 { Ok(()) }
+	else { Err(HtminlError::Unrestored(failed)) }
+}
+
+/// # Print to STDERR.
+///
+/// Print a message to STDERR, stripping the ANSI formatting if STDERR isn't
+/// a terminal, e.g. when redirected to a log file.
+fn eprint(msg: Msg) {
+	use std::io::IsTerminal;
+
+	if std::io::stderr().is_terminal() { msg.eprint(); }
+	else { msg.without_ansi().eprint(); }
+}
+
+/// # Skip Notice.
+///
+/// Format a skipped document's path along with the reason, including any
+/// underlying I/O or parser details.
+fn skipped(src: &Path, err: &HtminlError) -> Msg {
+	Msg::skipped(format!(
+		concat!("{} ", dim!("({})")),
+		src.display(),
+		err.reason(src),
+	))
+}
+
+/// # Document Sizes.
+///
+/// Format a document path along with its before/after sizes, e.g. for
+/// reporting changes in check or watch mode.
+fn sizes(src: &Path, before: NonZeroU64, after: NonZeroU64) -> String {
+	format!(
+		concat!("{} ", dim!("({} → {} bytes)")),
+		src.display(),
+		NiceU64::from(before),
+		NiceU64::from(after),
+	)
+}
+
+/// # Would-Be Savings.
+///
+/// Format a `Msg::with_bytes_saved`-style suffix for dry runs.
+fn would_save(sizes: BeforeAfter) -> String {
+	match (sizes.less(), sizes.less_percent()) {
+		(Some(saved), Some(per)) => format!(
+			concat!(" ", dim!("(Would save {} bytes, {}.)")),
+			NiceU64::from(saved),
+			NicePercent::from(per),
+		),
+		(Some(saved), None) => format!(
+			concat!(" ", dim!("(Would save {} bytes.)")),
+			NiceU64::from(saved),
+		),
+		(None, _) => concat!(" ", dim!("(No savings.)")).to_owned(),
 	}
 }
 
 /// # Summarize Results.
-fn summarize(progress: &Progless, total: u64) {
-	let elapsed = progress.finish();
+///
+/// For dry runs (`check`), the totals are worded as what _would_ be saved,
+/// matching the per-document "Review" lines.
+///
+/// If `breakdown` is set, the bytes saved by each category of optimization
+/// are listed beneath the totals.
+///
+/// If `top` is set, the per-document `records` are ranked and the top few
+/// listed too. If `profile` is set, they're mined for phase timings.
+fn summarize(
+	elapsed: Duration,
+	total: u64,
+	check: bool,
+	breakdown: bool,
+	top: Option<NonZeroUsize>,
+	profile: bool,
+	records: &[Record<'_>],
+) {
 	let skipped = SKIPPED.load(SeqCst);
-	if skipped == 0 {
-		progress.summary(MsgKind::Crunched, "document", "documents")
-	}
-	else {
-		// And summarize what we did do.
-		Msg::crunched(format!(
-			concat!(
-				"{}",
-				dim!("/"),
+	let msg =
+		if skipped == 0 {
+			format!(
 				"{} in {}.",
-			),
-			NiceU64::from(total - skipped),
-			total.nice_inflect("document", "documents"),
-			NiceElapsed::from(elapsed),
-		))
+				total.nice_inflect("document", "documents"),
+				NiceElapsed::from(elapsed),
+			)
+		}
+		// And summarize what we did do.
+		else {
+			format!(
+				concat!(
+					"{}",
+					dim!("/"),
+					"{} in {}.",
+				),
+				NiceU64::from(total - skipped),
+				total.nice_inflect("document", "documents"),
+				NiceElapsed::from(elapsed),
+			)
+		};
+	let sizes = BeforeAfter::from((BEFORE.load(SeqCst), AFTER.load(SeqCst)));
+	eprint(
+		if check { Msg::review(msg).with_suffix(would_save(sizes)) }
+		else { Msg::crunched(msg).with_bytes_saved(sizes) }
+	);
+
+	// Break it down?
+	if breakdown {
+		let width = Category::ALL.iter().map(|c| c.label().len()).max().unwrap_or(0);
+		for (kind, total) in Category::ALL.into_iter().zip(&SAVINGS) {
+			let total = total.load(SeqCst);
+			eprint(Msg::from(format!(
+				concat!("{:<width$} ", dim!("{} bytes")),
+				kind.label(),
+				NiceU64::from(total),
+				width = width,
+			)).with_indent(1).with_newline(true));
+		}
+	}
+
+	// Note the cached documents, if any.
+	let cached = CACHED.load(SeqCst);
+	if cached != 0 {
+		eprint(Msg::info(format!(
+			"{} unchanged since the last run.",
+			cached.nice_inflect("document was", "documents were"),
+		)));
 	}
-		.with_bytes_saved(BeforeAfter::from((
-			BEFORE.load(SeqCst),
-			AFTER.load(SeqCst),
-		)))
-		.eprint();
+
+	// Precompression gets its own lines.
+	precompress::summarize();
+
+	// As do the rankings and timings.
+	if let Some(n) = top { top::summarize(records, n); }
+	if profile { profile::summarize(records); }
 }
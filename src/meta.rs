@@ -0,0 +1,154 @@
+/*!
+# HTMinL: File Metadata.
+*/
+
+use std::{
+	fs::Metadata,
+	io::Result,
+	path::Path,
+};
+use write_atomic::filetime::FileTime;
+
+
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+/// # Metadata Preservation.
+///
+/// Permissions and ownership are always preserved: in-place rewrites keep
+/// their own, and copies saved elsewhere inherit the source's.
+///
+/// Access and modification times, on the other hand, are only kept if
+/// requested.
+pub(super) struct Preserve {
+	/// # Keep Source Times?
+	times: bool,
+
+	/// # Only Write Changes?
+	///
+	/// If true, destinations whose contents already match are left alone.
+	if_changed: bool,
+}
+
+impl Preserve {
+	#[must_use]
+	/// # New.
+	pub(super) const fn new(times: bool, if_changed: bool) -> Self {
+		Self { times, if_changed }
+	}
+
+	/// # Write File.
+	///
+	/// Atomically save `data` to `dst`, then sync the metadata from `src`
+	/// (and its original `meta`) as applicable.
+	///
+	/// ## Errors
+	///
+	/// This will bubble up any I/O errors encountered while writing or
+	/// updating the permissions or times.
+	pub(super) fn write(self, src: &Path, meta: &Metadata, dst: &Path, data: &[u8])
+	-> Result<()> {
+		if self.if_changed && dst != src && is_same(dst, data) { return Ok(()); }
+
+		write_atomic::write_file(dst, data)?;
+		self.sync(src, meta, dst)
+	}
+
+	/// # Copy File.
+	///
+	/// Atomically copy `src` to `dst` as-is, along with its permissions,
+	/// ownership, and times.
+	///
+	/// ## Errors
+	///
+	/// This will bubble up any I/O errors encountered while copying.
+	pub(super) fn copy(self, src: &Path, dst: &Path) -> Result<()> {
+		if self.if_changed && std::fs::read(src).is_ok_and(|raw| is_same(dst, &raw)) {
+			return Ok(());
+		}
+
+		write_atomic::copy_file(src, dst)
+	}
+
+	/// # Sync Metadata.
+	///
+	/// Copy the permissions and ownership from `src` to `dst` — unless
+	/// they're one and the same — and the original times too, if
+	/// preserving.
+	///
+	/// Ownership changes are best-effort, as they usually require elevated
+	/// privileges.
+	fn sync(self, src: &Path, meta: &Metadata, dst: &Path) -> Result<()> {
+		if dst != src {
+			std::fs::set_permissions(dst, meta.permissions())?;
+
+			#[cfg(unix)]
+			{
+				use std::os::unix::fs::MetadataExt;
+				let _res = std::os::unix::fs::chown(dst, Some(meta.uid()), Some(meta.gid()));
+			}
+		}
+
+		if self.times {
+			write_atomic::filetime::set_file_times(
+				dst,
+				FileTime::from_last_access_time(meta),
+				FileTime::from_last_modification_time(meta),
+			)?;
+		}
+
+		Ok(())
+	}
+}
+
+
+
+/// # Same Contents?
+///
+/// Returns `true` if the file at `dst` exists and holds exactly `data`.
+fn is_same(dst: &Path, data: &[u8]) -> bool {
+	std::fs::metadata(dst).is_ok_and(|m| m.len() == data.len() as u64) &&
+	std::fs::read(dst).is_ok_and(|raw| raw == data)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn t_write() {
+		let dir = std::env::temp_dir().join(format!("htminl-meta-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).expect("Unable to create temp dir.");
+		let src = dir.join("src.html");
+		let dst = dir.join("dst.html");
+		std::fs::write(&src, b"<p>  Hello  </p>").expect("Unable to write source.");
+
+		// Backdate the source.
+		let old = FileTime::from_unix_time(1_600_000_000, 0);
+		write_atomic::filetime::set_file_times(&src, old, old).expect("Unable to set times.");
+		let meta = std::fs::metadata(&src).expect("Missing source.");
+
+		// Without preservation, the time should be fresh.
+		Preserve::default().write(&src, &meta, &dst, b"<p> Hello </p>").expect("Write failed.");
+		let mtime = std::fs::metadata(&dst).map(|m| FileTime::from_last_modification_time(&m))
+			.expect("Missing destination.");
+		assert_ne!(mtime, old, "Modification time should have changed.");
+
+		// With it, the time should match the source.
+		Preserve::new(true, false).write(&src, &meta, &dst, b"<p> Hello </p>").expect("Write failed.");
+		let mtime = std::fs::metadata(&dst).map(|m| FileTime::from_last_modification_time(&m))
+			.expect("Missing destination.");
+		assert_eq!(mtime, old, "Modification time should have been preserved.");
+
+		// Identical writes should be skipped when only writing changes.
+		let new = FileTime::from_unix_time(1_700_000_000, 0);
+		write_atomic::filetime::set_file_times(&dst, new, new).expect("Unable to set times.");
+		Preserve::new(false, true).write(&src, &meta, &dst, b"<p> Hello </p>").expect("Write failed.");
+		let mtime = std::fs::metadata(&dst).map(|m| FileTime::from_last_modification_time(&m))
+			.expect("Missing destination.");
+		assert_eq!(mtime, new, "Identical file should have been left alone.");
+
+		let _res = std::fs::remove_dir_all(&dir);
+	}
+}
@@ -3,161 +3,208 @@
 */
 
 use crate::{
+	Backup,
+	ErrorContext,
 	HtminlError,
-	Tree,
+	MinifyOptions,
+	Output,
+	Preserve,
+	Phase,
+	Savings,
+	Timings,
 };
+use htminl::Minification;
 use std::{
 	num::NonZeroU64,
 	path::Path,
+	time::Instant,
 };
 
 
 
-/// # Fragment Open.
-const FRAGMENT_OPEN: &str = "<fragment-marker>";
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+/// # Minification Result.
+pub(super) struct Minified {
+	/// # Original Size.
+	pub(super) before: NonZeroU64,
 
-/// # Fragment Close.
-const FRAGMENT_CLOSE: &str = "</fragment-marker>";
+	/// # Minified Size.
+	///
+	/// This is only set if the output differs from the original.
+	pub(super) after: Option<NonZeroU64>,
+
+	/// # Fragment?
+	pub(super) fragment: bool,
+
+	/// # Savings Breakdown.
+	///
+	/// This is only populated if the output differs from the original.
+	pub(super) savings: Savings,
+
+	/// # Phase Timings.
+	pub(super) timings: Timings,
+}
+
+impl Minified {
+	#[must_use]
+	/// # Final Size.
+	///
+	/// Return the minified size, or the original if unchanged.
+	pub(super) const fn after_or_before(&self) -> NonZeroU64 {
+		if let Some(after) = self.after { after }
+		else { self.before }
+	}
+}
 
 
 
-#[expect(clippy::cast_possible_truncation, reason = "False positive.")]
 /// # Minify a Document (or Fragment).
 ///
 /// Read the raw HTML from a file, parse it into a tree, clean and minify said
 /// tree, turn it _back_ into HTML, and save it!
 ///
+/// Where it gets saved depends on the `output`. For dry runs, the results are
+/// returned as if it had been saved, but nothing is written. If the
+/// destination is some other path, unchanged documents are copied there
+/// as-is.
+///
+/// Files larger than `max_size`, if set, are skipped before reading to keep
+/// memory usage in check.
+///
+/// See `Preserve` for the metadata handling of saved files.
+///
+/// If `backup` is set, the original is backed up before being overwritten
+/// in-place. (See `Backup::save` for how existing backups are handled.)
+///
 /// ## Errors
 ///
-/// This will return an error if the file is unreadable, empty, or unparseable,
-/// or if issues are encountered when trying to re-save it.
-pub(super) fn minify(src: &Path) -> Result<(NonZeroU64, NonZeroU64), HtminlError> {
+/// This will return an error if the file is unreadable, empty, too big, or
+/// unparseable, or if issues are encountered when trying to re-save it.
+pub(super) fn minify(
+	src: &Path,
+	output: &Output,
+	max_size: Option<NonZeroU64>,
+	opts: &MinifyOptions,
+	preserve: Preserve,
+	backup: Option<&Backup>,
+) -> Result<Minified, HtminlError> {
+	// Figure out where it's going.
+	let dst = output.dst(src);
+	if dst.is_none() && ! output.is_dry_run() {
+		return Err(HtminlError::Save(
+			ErrorContext::new("The destination could not be determined.").with_path(src)
+		));
+	}
+
+	// Make sure it isn't too big.
+	let mut timings = Timings::default();
+	let now = Instant::now();
+	let meta = std::fs::metadata(src)
+		.map_err(|e| HtminlError::Read(ErrorContext::io(src, &e)))?;
+	if max_size.is_some_and(|max| max.get() < meta.len()) {
+		return Err(HtminlError::TooBig);
+	}
+
 	// Load the file.
-	let mut raw = std::fs::read_to_string(src).map_err(|_| HtminlError::Read)?;
+	let mut raw = std::fs::read_to_string(src)
+		.map_err(|e| HtminlError::Read(ErrorContext::io(src, &e)))?;
 	let before = u64::try_from(raw.len())
 		.ok()
 		.and_then(NonZeroU64::new)
 		.ok_or(HtminlError::EmptyFile)?;
-
-	// Replace all CRLF/CR instances with LF before parsing anything.
-	let mut changed = false;
-	while let Some(pos) = raw.find("\r\n") {
-		raw.replace_range(pos..pos + 2, "\n");
-		changed = true;
-	}
-	while let Some(pos) = raw.find('\r') {
-		raw.replace_range(pos..=pos, "\n");
-		changed = true;
-	}
-
-	// If this is a "fragment", wrap it so we can tease the relevant bit back
-	// out after processing.
-	let fragment = is_fragment(raw.as_bytes());
-	if fragment { make_whole(&mut raw); }
-
-	// Parse the document into a tree.
-	let dom = Tree::parse(raw.as_bytes())?;
-
-	// Try to save the results!
-	let mut out = dom.serialize(Some(before.get() as usize))?;
-
-	// If the original was a fragment, re-fragmentize it.
-	if fragment {
-		make_fragment(&mut raw); // Convert the original back too.
-		if ! make_fragment(&mut out) { return Err(HtminlError::Parse); }
-	}
+	timings.add(Phase::Read, now);
 
 	// Save it if different!
-	if (changed || raw != out) && ! out.is_empty() {
+	let Minification { html: out, fragment, savings, timings: more } =
+		htminl::minify_html(&mut raw, opts)
+			.map_err(|e| HtminlError::from(e).with_path(src))?;
+	timings.merge(more);
+	let now = Instant::now();
+	if let Some(out) = out {
 		let after = u64::try_from(out.len())
 			.ok()
 			.and_then(NonZeroU64::new)
 			.ok_or(HtminlError::EmptyFile)?;
-		write_atomic::write_file(src, out.as_bytes()).map_err(|_| HtminlError::Save)?;
-		return Ok((before, after));
-	}
-
-	// We didn't do anything.
-	Ok((before, before))
-}
-
-
-
-/// # Is Fragment.
-///
-/// This returns `false` if the document contains (case-insensitively)
-/// `<html`, `<body`, `</body>`, or `</html>`.
-fn is_fragment(src: &[u8]) -> bool {
-	for w in src.array_windows::<7>() {
-		if w[0] == b'<' {
-			match w[1] {
-				b'/' if w[6] == b'>' => {
-					let mid = &w[2..6];
-					if mid.eq_ignore_ascii_case(b"body") || mid.eq_ignore_ascii_case(b"html") {
-						return false;
-					}
-				},
-				b'b' | b'B' if w[2..5].eq_ignore_ascii_case(b"ody") => { return false; },
-				b'h' | b'H' if w[2..5].eq_ignore_ascii_case(b"tml") => { return false; },
-				_ => {},
+		if let Some(dst) = dst {
+			if let Some(backup) = backup && dst == src {
+				backup.save(src, |mut old| {
+					let out = htminl::minify_html(&mut old, opts).ok()?.html;
+					Some(out.unwrap_or(old))
+				})?;
 			}
+			preserve.write(src, &meta, &dst, out.as_bytes())
+				.map_err(|e| HtminlError::Save(ErrorContext::io(&dst, &e)))?;
 		}
+		else if let Some(diff) = output.diff() { diff.print(src, &raw, &out); }
+		timings.add(Phase::Write, now);
+		return Ok(Minified { before, after: Some(after), fragment, savings, timings });
+	}
+
+	// If the destination is somewhere else, copy the original over as-is.
+	if let Some(dst) = dst && dst != src {
+		preserve.copy(src, &dst)
+			.map_err(|e| HtminlError::Save(ErrorContext::io(&dst, &e)))?;
 	}
 
-	true
+	// We didn't do anything.
+	timings.add(Phase::Write, now);
+	Ok(Minified { before, after: None, fragment, savings: Savings::default(), timings })
 }
 
-/// # Make (Whole) Fragment.
+/// # Minify STDIN to STDOUT.
 ///
-/// The content is assumed to have been a "fragment" to begin with, but will
-/// only be altered — turned back into said fragment — if both the opening and
-/// closing markers are present.
+/// Read a single document (or fragment) from STDIN, minify it, and print the
+/// result to STDOUT.
 ///
-/// Returns `false` if either marker is missing, indicating corruption.
-fn make_fragment(src: &mut String) -> bool {
-	if
-		let Some(open) = src.find(FRAGMENT_OPEN) &&
-		let Some(close) = src.rfind(FRAGMENT_CLOSE)
-	{
-		src.truncate(close);
-		src.replace_range(..open + FRAGMENT_OPEN.len(), "");
-		true
-	}
-	else { false }
-}
-
-/// # Make (Fragment) Whole.
+/// For dry runs, nothing will be printed, unless a diff style is set, in
+/// which case the changes will be printed instead.
 ///
-/// Wrap fragmentary HTML in special marker tags so we can find the relevant
-/// part again after processing.
-fn make_whole(src: &mut String) {
-	src.insert_str(0, FRAGMENT_OPEN);
-	src.push_str(FRAGMENT_CLOSE);
-}
-
-
-
-#[cfg(test)]
-mod tests {
-	use super::*;
-
-	#[test]
-	fn t_fragment() {
-		assert!(
-			! is_fragment(include_bytes!("../skel/test-assets/blobfolio.com.html"))
-		);
-
-		let frag = include_str!("../skel/test-assets/fragment.html");
-		assert!(is_fragment(frag.as_bytes()));
-
-		// Now make it whole.
-		let mut frag2 = frag.to_owned();
-		make_whole(&mut frag2);
-		assert_ne!(frag, frag2); // Should be different now.
-
-		// Now make it a fragment again.
-		assert!(make_fragment(&mut frag2));
-		assert!(is_fragment(frag2.as_bytes()));
-		assert_eq!(frag, frag2);
+/// Returns `true` if the output differs from the original.
+///
+/// ## Errors
+///
+/// This will return an error if STDIN is unreadable, empty, too big, or
+/// unparseable, or if issues are encountered when trying to write to STDOUT.
+pub(super) fn minify_stdin(
+	output: &Output,
+	max_size: Option<NonZeroU64>,
+	opts: &MinifyOptions,
+) -> Result<bool, HtminlError> {
+	use std::io::{
+		Read,
+		Write,
+	};
+
+	// Load the document, but not (much) more than the limit.
+	let mut raw = Vec::new();
+	std::io::stdin()
+		.take(max_size.map_or(u64::MAX, |n| n.get().saturating_add(1)))
+		.read_to_end(&mut raw)
+		.map_err(|e| HtminlError::Read(ErrorContext::io(Path::new("-"), &e)))?;
+	if raw.is_empty() { return Err(HtminlError::EmptyFile); }
+	if max_size.is_some_and(|n| n.get() < raw.len() as u64) {
+		return Err(HtminlError::TooBig);
+	}
+	let mut raw = String::from_utf8(raw).map_err(|_| HtminlError::Read(
+		ErrorContext::new("The document is not valid UTF-8.").with_path(Path::new("-"))
+	))?;
+
+	// Minify it.
+	let out = htminl::minify_html(&mut raw, opts)?.html;
+	let changed = out.is_some();
+
+	// Print the results — minified or not — unless this is a dry run.
+	if output.is_dry_run() {
+		if let Some(diff) = output.diff() && let Some(out) = out.as_ref() {
+			diff.print(Path::new("-"), &raw, out);
+		}
+	}
+	else {
+		let mut stdout = std::io::stdout().lock();
+		stdout.write_all(out.as_ref().unwrap_or(&raw).as_bytes())
+			.and_then(|()| stdout.flush())
+			.map_err(|e| HtminlError::Save(ErrorContext::io(Path::new("-"), &e)))?;
 	}
+
+	Ok(changed)
 }
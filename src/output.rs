@@ -0,0 +1,228 @@
+/*!
+# HTMinL: Output.
+*/
+
+use crate::Diff;
+use std::{
+	borrow::Cow,
+	ffi::OsString,
+	path::{
+		Path,
+		PathBuf,
+	},
+};
+
+
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// # Output Destination.
+///
+/// This determines where — if anywhere — minified documents get saved.
+pub(super) enum Output {
+	/// # Overwrite the Originals.
+	InPlace,
+
+	/// # Don't Save Anything.
+	///
+	/// If a diff style is set, changes will be printed instead.
+	DryRun(Option<Diff>),
+
+	/// # Mirror Into a Directory.
+	Dir(Mirror),
+
+	/// # Save Alongside the Originals.
+	///
+	/// The suffix is inserted before the file extension, e.g. `min` turns
+	/// `foo.html` into `foo.min.html`.
+	Sibling(String),
+}
+
+impl Output {
+	/// # New Output Directory.
+	///
+	/// Returns `None` if the directory cannot be created or canonicalized.
+	pub(super) fn dir(dst: &Path, roots: Vec<PathBuf>) -> Option<Self> {
+		Mirror::new(dst, roots).map(Self::Dir)
+	}
+
+	/// # New Sibling Suffix.
+	///
+	/// Return a sibling destination using the given suffix. A leading dot is
+	/// optional.
+	///
+	/// Returns `None` if the suffix is empty or contains path separators or
+	/// (non-leading) dots.
+	pub(super) fn sibling(suffix: &str) -> Option<Self> {
+		let suffix = suffix.trim();
+		let suffix = suffix.strip_prefix('.').unwrap_or(suffix);
+		if suffix.is_empty() || suffix.contains(['.', '/', '\\']) { None }
+		else { Some(Self::Sibling(suffix.to_owned())) }
+	}
+
+	#[must_use]
+	/// # Is Dry Run?
+	pub(super) const fn is_dry_run(&self) -> bool { matches!(self, Self::DryRun(_)) }
+
+	#[must_use]
+	/// # Diff Style.
+	///
+	/// Return the diff style, if any.
+	pub(super) const fn diff(&self) -> Option<Diff> {
+		if let Self::DryRun(diff) = self { *diff }
+		else { None }
+	}
+
+	#[must_use]
+	/// # Output Directory.
+	///
+	/// Return the output directory, if any, so it can be excluded from the
+	/// search.
+	pub(super) fn out_dir(&self) -> Option<&Path> {
+		if let Self::Dir(mirror) = self { Some(mirror.dir()) }
+		else { None }
+	}
+
+	#[must_use]
+	/// # Is Output Path?
+	///
+	/// Returns `true` if `src` looks like a file previously written by this
+	/// output (i.e. already has the sibling suffix) and should therefore be
+	/// skipped.
+	///
+	/// Extensionless sources end up _with_ the suffix as their extension, so
+	/// that counts too.
+	pub(super) fn is_dst(&self, src: &Path) -> bool {
+		if let Self::Sibling(suffix) = self {
+			src.extension().is_some_and(|s| s.eq_ignore_ascii_case(suffix)) ||
+			src.file_stem()
+				.and_then(|s| Path::new(s).extension())
+				.is_some_and(|s| s.eq_ignore_ascii_case(suffix))
+		}
+		else { false }
+	}
+
+	#[must_use]
+	/// # Destination Path.
+	///
+	/// Return the path the minified version of `src` should be written to,
+	/// or `None` for dry runs, or if `src` doesn't fall under any of the
+	/// roots.
+	pub(super) fn dst<'a>(&self, src: &'a Path) -> Option<Cow<'a, Path>> {
+		match self {
+			Self::InPlace => Some(Cow::Borrowed(src)),
+			Self::DryRun(_) => None,
+			Self::Dir(mirror) => mirror.path(src).map(Cow::Owned),
+			Self::Sibling(suffix) => Some(Cow::Owned(src.extension().map_or_else(
+				|| src.with_extension(suffix),
+				|ext| {
+					let mut ext2 = OsString::from(suffix);
+					ext2.push(".");
+					ext2.push(ext);
+					src.with_extension(ext2)
+				},
+			))),
+		}
+	}
+}
+
+
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// # Mirrored Directory.
+///
+/// This maps documents to the same relative location within some other
+/// directory, e.g. for `--out-dir` and `--backup=<DIR>`.
+pub(super) struct Mirror {
+	/// # Directory.
+	pub(super) dst: PathBuf,
+
+	/// # Source Roots.
+	///
+	/// Canonical source directories, shortest first, used to work out the
+	/// relative location of each document.
+	pub(super) roots: Vec<PathBuf>,
+}
+
+impl Mirror {
+	/// # New.
+	///
+	/// Create the directory if missing and return a mirror for it, relative
+	/// to the (canonical) source `roots`.
+	///
+	/// Returns `None` if the directory cannot be created or canonicalized.
+	pub(super) fn new(dst: &Path, roots: Vec<PathBuf>) -> Option<Self> {
+		std::fs::create_dir_all(dst).ok()?;
+		let dst = std::fs::canonicalize(dst).ok()?;
+		Some(Self { dst, roots })
+	}
+
+	#[must_use]
+	/// # Directory.
+	pub(super) fn dir(&self) -> &Path { &self.dst }
+
+	#[must_use]
+	/// # Mirrored Path.
+	///
+	/// Return the mirrored path for `src`, or `None` if `src` doesn't fall
+	/// under any of the roots.
+	pub(super) fn path(&self, src: &Path) -> Option<PathBuf> {
+		self.roots.iter()
+			.find_map(|r| src.strip_prefix(r).ok())
+			.map(|rel| self.dst.join(rel))
+	}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn t_dst() {
+		let src = Path::new("/src/site/blog/index.html");
+		assert_eq!(Output::InPlace.dst(src).as_deref(), Some(src));
+		assert_eq!(Output::DryRun(None).dst(src), None);
+
+		// The shortest root should win.
+		let out = Output::Dir(Mirror {
+			dst: PathBuf::from("/out"),
+			roots: vec![PathBuf::from("/src/site"), PathBuf::from("/src/site/blog")],
+		});
+		assert_eq!(
+			out.dst(src).as_deref(),
+			Some(Path::new("/out/blog/index.html")),
+		);
+
+		// Unrelated paths have nowhere to go.
+		assert_eq!(out.dst(Path::new("/elsewhere/index.html")), None);
+	}
+
+	#[test]
+	fn t_sibling() {
+		// Leading dots are fine, but other nonsense is not.
+		let out = Output::sibling(".min").expect("Sibling failed.");
+		assert_eq!(out, Output::Sibling("min".to_owned()));
+		for i in ["", ".", "a.b", "a/b"] {
+			assert!(Output::sibling(i).is_none(), "{i:?}");
+		}
+
+		// Destinations.
+		assert_eq!(
+			out.dst(Path::new("/src/index.html")).as_deref(),
+			Some(Path::new("/src/index.min.html")),
+		);
+		assert_eq!(
+			out.dst(Path::new("/src/index")).as_deref(),
+			Some(Path::new("/src/index.min")),
+		);
+
+		// Outputs.
+		assert!(out.is_dst(Path::new("/src/index.min.html")));
+		assert!(out.is_dst(Path::new("/src/index.MIN.html")));
+		assert!(out.is_dst(Path::new("/src/index.min")));
+		assert!(! out.is_dst(Path::new("/src/index.html")));
+		assert!(! out.is_dst(Path::new("/src/min.html")));
+		assert!(! Output::InPlace.is_dst(Path::new("/src/index.min.html")));
+	}
+}
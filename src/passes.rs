@@ -0,0 +1,154 @@
+/*!
+# HTMinL: Custom Passes.
+*/
+
+use htminl::{
+	Element,
+	Pass,
+};
+use serde::Deserialize;
+
+
+
+/// # URL Attributes.
+///
+/// These are the attributes `ConfigPass::RewriteUrls` looks at.
+const URL_ATTRS: [&str; 5] = ["action", "href", "poster", "src", "srcset"];
+
+
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case", deny_unknown_fields)]
+/// # Configurable Pass.
+///
+/// These are the custom passes that can be enabled via `[[pass]]` tables in
+/// `htminl.toml`. They run in the order listed, ahead of the built-in
+/// whitespace minification.
+pub(super) enum ConfigPass {
+	/// # Strip Attributes.
+	StripAttrs {
+		/// # Attribute Names.
+		attrs: Vec<String>,
+	},
+
+	/// # Rewrite URL Prefixes.
+	RewriteUrls {
+		/// # Old Prefix.
+		from: String,
+
+		/// # New Prefix.
+		to: String,
+	},
+}
+
+impl Pass for ConfigPass {
+	fn name(&self) -> &str {
+		match self {
+			Self::StripAttrs { .. } => "strip-attrs",
+			Self::RewriteUrls { .. } => "rewrite-urls",
+		}
+	}
+
+	fn element(&self, el: &Element<'_>) {
+		match self {
+			Self::StripAttrs { attrs } => {
+				el.retain_attrs(|k, _| ! attrs.iter().any(|a| a.eq_ignore_ascii_case(k)));
+			},
+			Self::RewriteUrls { from, to } =>
+				for key in URL_ATTRS {
+					if
+						let Some(old) = el.attr(key) &&
+						let Some(new) = rewrite(&old, from, to, key == "srcset")
+					{
+						el.set_attr(key, &new);
+					}
+				},
+		}
+	}
+}
+
+impl ConfigPass {
+	/// # Validate.
+	///
+	/// ## Errors
+	///
+	/// This will return an error — as a string — if the pass would have
+	/// nothing to do.
+	pub(super) fn validate(&self) -> Result<(), String> {
+		match self {
+			Self::StripAttrs { attrs } =>
+				if attrs.iter().any(|a| a.trim().is_empty()) {
+					return Err("invalid strip-attrs".to_owned());
+				},
+			Self::RewriteUrls { from, .. } =>
+				if from.trim().is_empty() {
+					return Err("invalid rewrite-urls".to_owned());
+				},
+		}
+		Ok(())
+	}
+}
+
+
+
+/// # Rewrite URL.
+///
+/// Replace the `from` prefix of the URL — or each of the URLs, in the case
+/// of `srcset` — with `to`, returning the new value if different.
+fn rewrite(src: &str, from: &str, to: &str, srcset: bool) -> Option<String> {
+	if srcset {
+		if ! src.contains(from) { return None; }
+		let out = src.split(',')
+			.map(|c| {
+				let url = c.trim_start();
+				url.strip_prefix(from).map_or_else(
+					|| c.to_owned(),
+					|rest| format!("{}{to}{rest}", &c[..c.len() - url.len()]),
+				)
+			})
+			.collect::<Vec<_>>()
+			.join(",");
+		if out == src { None } else { Some(out) }
+	}
+	else {
+		src.trim_start().strip_prefix(from).map(|rest| format!("{to}{rest}"))
+	}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use htminl::MinifyOptions;
+
+	#[test]
+	fn t_rewrite() {
+		assert_eq!(rewrite("/a.png", "https://", "//", false), None);
+		assert_eq!(
+			rewrite(" https://old.com/a.png", "https://old.com/", "/cdn/", false).as_deref(),
+			Some("/cdn/a.png"),
+		);
+		assert_eq!(
+			rewrite("https://old.com/a.png 1x, /b.png 2x,https://old.com/c.png 3x", "https://old.com/", "/", true).as_deref(),
+			Some("/a.png 1x, /b.png 2x,/c.png 3x"),
+		);
+	}
+
+	#[test]
+	fn t_passes() {
+		let opts = MinifyOptions::default()
+			.with_pass(ConfigPass::StripAttrs { attrs: vec!["data-test".to_owned()] })
+			.with_pass(ConfigPass::RewriteUrls {
+				from: "https://old.com/".to_owned(),
+				to: "https://new.com/".to_owned(),
+			});
+
+		let mut raw = String::from(r#"<a data-test="1" href="https://old.com/x">X</a> <img DATA-TEST src="https://old.com/y.png">"#);
+		let out = htminl::minify_html(&mut raw, &opts).expect("Minification failed.");
+		assert_eq!(
+			out.html.as_deref(),
+			Some(r#"<a href="https://new.com/x">X</a> <img src="https://new.com/y.png">"#),
+		);
+	}
+}
@@ -0,0 +1,374 @@
+/*!
+# HTMinL: Precompression.
+*/
+
+use crate::{
+	ErrorContext,
+	HtminlError,
+};
+use brotli::enc::{
+	backward_references::BrotliEncoderMode,
+	BrotliEncoderParams,
+};
+use dactyl::{
+	NiceU64,
+	traits::NiceInflection,
+};
+use flate2::{
+	Compression,
+	write::GzEncoder,
+};
+use fyi_msg::{
+	BeforeAfter,
+	Msg,
+};
+use std::{
+	ffi::OsString,
+	io::Write,
+	path::{
+		Path,
+		PathBuf,
+	},
+	sync::atomic::{
+		AtomicU64,
+		Ordering::SeqCst,
+	},
+};
+
+
+
+/// # Gzip Totals.
+static GZIP: Totals = Totals::new();
+
+/// # Brotli Totals.
+static BROTLI: Totals = Totals::new();
+
+
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+/// # Precompression Settings.
+///
+/// This holds the encoding(s) to generate precompressed siblings for, e.g.
+/// `index.html.gz` and `index.html.br`.
+pub(super) struct Precompress {
+	/// # Gzip?
+	gzip: bool,
+
+	/// # Brotli?
+	brotli: bool,
+}
+
+impl Precompress {
+	#[must_use]
+	/// # From CLI.
+	///
+	/// Parse a comma-separated list of encodings — `gzip` and/or `brotli`, or
+	/// `gz`/`br` for short.
+	///
+	/// Returns `None` if the list is empty or contains anything else.
+	pub(super) fn from_cli(src: &str) -> Option<Self> {
+		let mut out = Self::default();
+		for enc in src.split(',') {
+			match enc.trim().to_ascii_lowercase().as_str() {
+				"gzip" | "gz" => { out.gzip = true; },
+				"brotli" | "br" => { out.brotli = true; },
+				_ => return None,
+			}
+		}
+		Some(out)
+	}
+
+	#[must_use]
+	/// # From Manifest.
+	///
+	/// Parse the `as_str` representation, e.g. from the cache manifest.
+	pub(super) fn from_manifest(src: &str) -> Option<Self> {
+		if src == "-" { Some(Self::default()) }
+		else { Self::from_cli(src) }
+	}
+
+	#[must_use]
+	/// # As Str.
+	///
+	/// Return the encodings as a comma-separated list, or `-` if none.
+	pub(super) const fn as_str(self) -> &'static str {
+		match (self.gzip, self.brotli) {
+			(true, true) => "gz,br",
+			(true, false) => "gz",
+			(false, true) => "br",
+			(false, false) => "-",
+		}
+	}
+
+	/// # Sibling Paths.
+	///
+	/// Return the precompressed sibling paths for `src`, e.g.
+	/// `index.html.gz`.
+	pub(super) fn siblings(self, src: &Path) -> impl Iterator<Item=PathBuf> {
+		[Encoding::Gzip, Encoding::Brotli].into_iter()
+			.filter(move |enc| self.has(*enc))
+			.map(|enc| enc.sibling(src))
+	}
+
+	/// # Precompress.
+	///
+	/// Read the (minified) document at `src` and save compressed copies of it
+	/// alongside, adding to the running totals.
+	///
+	/// Copies that would be larger than the source are skipped, and any
+	/// existing (and now stale) copies removed.
+	///
+	/// Returns the encodings actually saved.
+	///
+	/// ## Errors
+	///
+	/// This will return an error if the document cannot be read, or any of
+	/// the copies cannot be saved. In the latter case, the stale copy, if
+	/// any, is removed, and the other encoding is still attempted.
+	pub(super) fn encode(self, src: &Path) -> Result<Self, HtminlError> {
+		let mut saved = Self::default();
+		let raw = std::fs::read(src)
+			.map_err(|e| HtminlError::Read(ErrorContext::io(src, &e)))?;
+		if raw.is_empty() { return Ok(saved); }
+
+		let mut err = None;
+		for enc in [Encoding::Gzip, Encoding::Brotli] {
+			if ! self.has(enc) { continue; }
+
+			let dst = enc.sibling(src);
+			if let Some(out) = enc.encode(&raw).filter(|out| out.len() < raw.len()) {
+				match write_atomic::write_file(&dst, &out) {
+					Ok(()) => {
+						enc.totals().add(raw.len() as u64, out.len() as u64);
+						saved.set(enc);
+					},
+					Err(e) => {
+						if dst.is_file() { let _res = std::fs::remove_file(&dst); }
+						if err.is_none() {
+							err = Some(HtminlError::Save(ErrorContext::io(&dst, &e)));
+						}
+					},
+				}
+			}
+			else if dst.is_file() { let _res = std::fs::remove_file(&dst); }
+		}
+
+		err.map_or(Ok(saved), Err)
+	}
+
+	#[must_use]
+	/// # Has Encoding?
+	const fn has(self, enc: Encoding) -> bool {
+		match enc {
+			Encoding::Gzip => self.gzip,
+			Encoding::Brotli => self.brotli,
+		}
+	}
+
+	/// # Set Encoding.
+	const fn set(&mut self, enc: Encoding) {
+		match enc {
+			Encoding::Gzip => { self.gzip = true; },
+			Encoding::Brotli => { self.brotli = true; },
+		}
+	}
+}
+
+
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+/// # Encoding.
+enum Encoding {
+	/// # Gzip.
+	Gzip,
+
+	/// # Brotli.
+	Brotli,
+}
+
+impl Encoding {
+	#[must_use]
+	/// # File Extension.
+	const fn ext(self) -> &'static str {
+		match self {
+			Self::Gzip => ".gz",
+			Self::Brotli => ".br",
+		}
+	}
+
+	#[must_use]
+	/// # Sibling Path.
+	fn sibling(self, src: &Path) -> PathBuf {
+		let mut dst = OsString::from(src.as_os_str());
+		dst.push(self.ext());
+		PathBuf::from(dst)
+	}
+
+	#[must_use]
+	/// # Label.
+	const fn label(self) -> &'static str {
+		match self {
+			Self::Gzip => "gzipped",
+			Self::Brotli => "brotlied",
+		}
+	}
+
+	#[must_use]
+	/// # Totals.
+	const fn totals(self) -> &'static Totals {
+		match self {
+			Self::Gzip => &GZIP,
+			Self::Brotli => &BROTLI,
+		}
+	}
+
+	#[must_use]
+	/// # Encode.
+	///
+	/// Compress `raw` using the best available settings, returning `None` if
+	/// it fails for whatever reason.
+	fn encode(self, raw: &[u8]) -> Option<Vec<u8>> {
+		let mut out = Vec::with_capacity(raw.len() / 2);
+		match self {
+			Self::Gzip => {
+				let mut enc = GzEncoder::new(out, Compression::best());
+				enc.write_all(raw).ok()?;
+				out = enc.finish().ok()?;
+			},
+			Self::Brotli => {
+				let params = BrotliEncoderParams {
+					mode: BrotliEncoderMode::BROTLI_MODE_TEXT,
+					quality: 11,
+					lgwin: 22,
+					size_hint: raw.len(),
+					..BrotliEncoderParams::default()
+				};
+				brotli::BrotliCompress(&mut &raw[..], &mut out, &params).ok()?;
+			},
+		}
+		Some(out)
+	}
+}
+
+
+
+/// # Running Totals.
+struct Totals {
+	/// # Document Count.
+	count: AtomicU64,
+
+	/// # Total Size Before.
+	before: AtomicU64,
+
+	/// # Total Size After.
+	after: AtomicU64,
+}
+
+impl Totals {
+	#[must_use]
+	/// # New.
+	const fn new() -> Self {
+		Self {
+			count: AtomicU64::new(0),
+			before: AtomicU64::new(0),
+			after: AtomicU64::new(0),
+		}
+	}
+
+	/// # Add.
+	fn add(&self, before: u64, after: u64) {
+		self.count.fetch_add(1, SeqCst);
+		self.before.fetch_add(before, SeqCst);
+		self.after.fetch_add(after, SeqCst);
+	}
+}
+
+
+
+/// # Summarize Results.
+///
+/// Print a summary line for each encoding that was actually used.
+pub(super) fn summarize() {
+	for enc in [Encoding::Gzip, Encoding::Brotli] {
+		let totals = enc.totals();
+		let count = totals.count.load(SeqCst);
+		if count == 0 { continue; }
+
+		crate::eprint(
+			Msg::crunched(format!(
+				"{} {}, {} bytes total.",
+				count.nice_inflect("document", "documents"),
+				enc.label(),
+				NiceU64::from(totals.after.load(SeqCst)),
+			))
+				.with_bytes_saved(BeforeAfter::from((
+					totals.before.load(SeqCst),
+					totals.after.load(SeqCst),
+				)))
+		);
+	}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn t_from_cli() {
+		assert_eq!(
+			Precompress::from_cli("gzip,brotli"),
+			Some(Precompress { gzip: true, brotli: true }),
+		);
+		assert_eq!(
+			Precompress::from_cli(" BR "),
+			Some(Precompress { gzip: false, brotli: true }),
+		);
+		assert_eq!(Precompress::from_cli("gz,gz"), Some(Precompress { gzip: true, brotli: false }));
+		for i in ["", "gzip,", "zstd", "gzip,zstd", "-"] {
+			assert!(Precompress::from_cli(i).is_none(), "{i:?}");
+		}
+
+		// Manifest round trip.
+		for i in ["-", "gz", "br", "gz,br"] {
+			assert_eq!(Precompress::from_manifest(i).map(Precompress::as_str), Some(i));
+		}
+	}
+
+	#[test]
+	fn t_encode() {
+		let raw = "<p>Hello World</p>".repeat(100);
+		for enc in [Encoding::Gzip, Encoding::Brotli] {
+			let out = enc.encode(raw.as_bytes()).expect("Encoding failed.");
+			assert!(out.len() < raw.len(), "{enc:?}");
+		}
+
+		// Gzip's magic number is easy enough to verify too.
+		let out = Encoding::Gzip.encode(raw.as_bytes()).expect("Encoding failed.");
+		assert!(out.starts_with(&[0x1f, 0x8b]), "Missing gzip magic.");
+	}
+
+	#[test]
+	fn t_encode_fail() {
+		let dir = std::env::temp_dir().join(format!("htminl-precompress-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).expect("Unable to create temp dir.");
+		let src = dir.join("index.html");
+		std::fs::write(&src, "<p>Hello World</p>".repeat(100)).expect("Unable to write source.");
+
+		// A directory in the way should fail the gzip copy, but not the
+		// brotli one.
+		std::fs::create_dir_all(dir.join("index.html.gz")).expect("Unable to create dir.");
+		let both = Precompress { gzip: true, brotli: true };
+		let err = both.encode(&src).expect_err("Gzip should have failed.");
+		std::assert_matches!(err, HtminlError::Save(_));
+		assert!(err.to_string().contains("index.html.gz"), "Missing path.");
+		assert!(dir.join("index.html.br").is_file(), "Brotli should have been saved.");
+
+		// Without the obstruction, all is well.
+		std::fs::remove_dir(dir.join("index.html.gz")).expect("Unable to remove dir.");
+		assert_eq!(both.encode(&src).ok(), Some(both));
+
+		let _res = std::fs::remove_dir_all(&dir);
+	}
+}
@@ -0,0 +1,122 @@
+/*!
+# HTMinL: Profiling.
+*/
+
+use crate::{
+	Phase,
+	report::Record,
+	Timings,
+};
+use dactyl::NicePercent;
+use fyi_msg::{
+	fyi_ansi::dim,
+	Msg,
+};
+use std::{
+	fmt::Write,
+	time::Duration,
+};
+
+
+
+/// # Slowest Documents to List.
+const SLOWEST: usize = 10;
+
+
+
+/// # Summarize.
+///
+/// Print the slowest documents — with their per-phase breakdowns — followed
+/// by the phase totals for the run.
+///
+/// Only freshly crunched documents are considered; skipped and cached ones
+/// have no phases to speak of.
+pub(super) fn summarize(records: &[Record<'_>]) {
+	let mut docs: Vec<(&Record, Timings)> = records.iter()
+		.filter_map(|r|
+			if r.cached { None }
+			else { r.result.as_ref().ok().map(|m| (r, m.timings)) }
+		)
+		.collect();
+	if docs.is_empty() { return; }
+
+	// Add up the totals.
+	let mut totals = Timings::default();
+	for (_, t) in &docs { totals.merge(*t); }
+
+	// The slowest documents first.
+	docs.sort_unstable_by(|(a, _), (b, _)| b.elapsed.cmp(&a.elapsed).then_with(|| a.path.cmp(&b.path)));
+	docs.truncate(SLOWEST);
+
+	crate::eprint(Msg::info("Slowest documents:"));
+	for (r, t) in docs {
+		crate::eprint(Msg::from(format!(
+			concat!("{} {} ", dim!("({})")),
+			r.path.display(),
+			ms(r.elapsed),
+			phases(t),
+		)).with_indent(1).with_newline(true));
+	}
+
+	let sum = Phase::ALL.into_iter().map(|p| totals.get(p)).sum::<Duration>();
+	let width = Phase::ALL.iter().map(|p| p.label().len()).max().unwrap_or(0);
+	crate::eprint(Msg::info("Phase totals:"));
+	for phase in Phase::ALL {
+		let time = totals.get(phase);
+		crate::eprint(Msg::from(format!(
+			concat!("{:<width$} {} ", dim!("({})")),
+			phase.label(),
+			ms(time),
+			NicePercent::from(time.as_secs_f64() / sum.as_secs_f64()),
+			width = width,
+		)).with_indent(1).with_newline(true));
+	}
+}
+
+
+
+/// # Format Milliseconds.
+fn ms(time: Duration) -> String {
+	format!("{:.3}ms", time.as_secs_f64() * 1000.0)
+}
+
+/// # Format Phases.
+///
+/// Return a compact list of each phase's (non-zero) time, e.g.
+/// `read 0.010ms, parse 1.234ms, …`.
+fn phases(timings: Timings) -> String {
+	let mut out = String::new();
+	for phase in Phase::ALL {
+		let time = timings.get(phase);
+		if time.is_zero() { continue; }
+		if ! out.is_empty() { out.push_str(", "); }
+		let _res = write!(out, "{} {}", phase.label(), ms(time));
+	}
+	out
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::time::Instant;
+
+	#[test]
+	fn t_phases() {
+		assert_eq!(phases(Timings::default()), "");
+		assert_eq!(ms(Duration::from_micros(1500)), "1.500ms");
+
+		// Real timings won't be predictable, but the structure will be.
+		let mut timings = Timings::default();
+		let now = Instant::now();
+		std::thread::sleep(Duration::from_millis(1));
+		timings.add(Phase::Parse, now);
+		timings.add(Phase::Write, now);
+
+		let out = phases(timings);
+		assert!(out.starts_with("parse "), "{out}");
+		assert!(out.contains(", write "), "{out}");
+		assert!(! out.contains("read"), "{out}");
+	}
+}
@@ -0,0 +1,250 @@
+/*!
+# HTMinL: Reports.
+*/
+
+use crate::{
+	ErrorContext,
+	HtminlError,
+	minify::Minified,
+};
+use std::{
+	borrow::Cow,
+	fmt::Write,
+	path::{
+		Path,
+		PathBuf,
+	},
+	time::Duration,
+};
+
+
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// # (JSON) Report Destination.
+pub(super) enum Report {
+	/// # Print to STDOUT.
+	Stdout,
+
+	/// # Save to File.
+	File(PathBuf),
+}
+
+impl Report {
+	#[must_use]
+	/// # From CLI.
+	///
+	/// Parse a `--report` value, which should be `json`, optionally followed
+	/// by `=FILE`. (A `FILE` of `-` is equivalent to omitting it.)
+	///
+	/// Returns `None` if the value is invalid.
+	pub(super) fn from_cli(src: &str) -> Option<Self> {
+		let (kind, file) = match src.split_once('=') {
+			Some((a, b)) => (a.trim(), Some(b.trim())),
+			None => (src.trim(), None),
+		};
+
+		if ! kind.eq_ignore_ascii_case("json") { return None; }
+		match file {
+			None | Some("-") => Some(Self::Stdout),
+			Some("") => None,
+			Some(f) => Some(Self::File(PathBuf::from(f))),
+		}
+	}
+
+	/// # Write Report.
+	///
+	/// Sort the records by path, encode them as a JSON array, and print or
+	/// save the result.
+	///
+	/// ## Errors
+	///
+	/// This will return an error if the report cannot be written.
+	pub(super) fn write(&self, records: &mut [Record<'_>]) -> Result<(), HtminlError> {
+		use std::io::Write;
+
+		records.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+
+		let mut out = String::with_capacity(4 + records.len() * 192);
+		out.push('[');
+		for (k, r) in records.iter().enumerate() {
+			if k != 0 { out.push(','); }
+			out.push_str("\n\t");
+			r.push_json(&mut out);
+		}
+		out.push_str("\n]\n");
+
+		match self {
+			Self::Stdout => {
+				let mut stdout = std::io::stdout().lock();
+				stdout.write_all(out.as_bytes()).and_then(|()| stdout.flush())
+			},
+			Self::File(dst) => write_atomic::write_file(dst, out.as_bytes()),
+		}
+			.map_err(|_| HtminlError::ReportSave)
+	}
+}
+
+
+
+#[derive(Debug)]
+/// # Document Record.
+///
+/// This holds the outcome of a single document for reporting purposes.
+pub(super) struct Record<'a> {
+	/// # Source Path.
+	pub(super) path: Cow<'a, Path>,
+
+	/// # Result.
+	pub(super) result: Result<Minified, HtminlError>,
+
+	/// # Cached?
+	///
+	/// True if the document was skipped as unchanged since the last run.
+	pub(super) cached: bool,
+
+	/// # Elapsed Time.
+	pub(super) elapsed: Duration,
+}
+
+impl Record<'_> {
+	/// # Push JSON.
+	///
+	/// Encode the record as a JSON object and push it onto `out`.
+	fn push_json(&self, out: &mut String) {
+		out.push_str("{\"path\":");
+		push_json_str(out, &self.path.to_string_lossy());
+
+		match &self.result {
+			Ok(m) => {
+				let _res = write!(
+					out,
+					",\"before\":{},\"after\":{},\"changed\":{},\"fragment\":{}",
+					m.before,
+					m.after_or_before(),
+					m.after.is_some(),
+					m.fragment,
+				);
+			},
+			Err(_) => {
+				out.push_str(",\"before\":null,\"after\":null,\"changed\":false,\"fragment\":null");
+			},
+		}
+
+		let _res = write!(
+			out,
+			",\"cached\":{},\"elapsed_ms\":{:.3}",
+			self.cached,
+			self.elapsed.as_secs_f64() * 1000.0,
+		);
+
+		if let Err(e) = &self.result {
+			out.push_str(",\"error\":");
+			push_json_str(out, e.kind());
+			out.push_str(",\"reason\":");
+			push_json_str(out, &e.reason(&self.path));
+			out.push_str(",\"io_error\":");
+			match e.context().and_then(ErrorContext::io_kind) {
+				Some(kind) => push_json_str(out, &format!("{kind:?}")),
+				None => out.push_str("null"),
+			}
+		}
+		else { out.push_str(",\"error\":null,\"reason\":null,\"io_error\":null"); }
+
+		out.push('}');
+	}
+}
+
+
+
+/// # Push JSON String.
+///
+/// Push a quoted and escaped JSON string onto `out`.
+fn push_json_str(out: &mut String, src: &str) {
+	out.push('"');
+	for c in src.chars() {
+		match c {
+			'"' =>  { out.push_str("\\\""); },
+			'\\' => { out.push_str("\\\\"); },
+			'\n' => { out.push_str("\\n"); },
+			'\r' => { out.push_str("\\r"); },
+			'\t' => { out.push_str("\\t"); },
+			c if c.is_control() => { let _res = write!(out, "\\u{:04x}", u32::from(c)); },
+			c => { out.push(c); },
+		}
+	}
+	out.push('"');
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::num::NonZeroU64;
+
+	#[test]
+	fn t_from_cli() {
+		assert_eq!(Report::from_cli("json"), Some(Report::Stdout));
+		assert_eq!(Report::from_cli("JSON=-"), Some(Report::Stdout));
+		assert_eq!(
+			Report::from_cli("json=/tmp/report.json"),
+			Some(Report::File(PathBuf::from("/tmp/report.json"))),
+		);
+		assert_eq!(Report::from_cli("json="), None);
+		assert_eq!(Report::from_cli("xml"), None);
+	}
+
+	#[test]
+	fn t_push_json() {
+		let mut out = String::new();
+		push_json_str(&mut out, "Hello \"World\"\\\n\x01");
+		assert_eq!(out, r#""Hello \"World\"\\\n\u0001""#);
+
+		out.clear();
+		let record = Record {
+			path: Cow::Borrowed(Path::new("/foo/bar.html")),
+			result: Ok(Minified {
+				before: NonZeroU64::new(100).unwrap(),
+				after: NonZeroU64::new(90),
+				fragment: true,
+				savings: crate::Savings::default(),
+				timings: crate::Timings::default(),
+			}),
+			cached: false,
+			elapsed: Duration::from_micros(1500),
+		};
+		record.push_json(&mut out);
+		assert_eq!(
+			out,
+			r#"{"path":"/foo/bar.html","before":100,"after":90,"changed":true,"fragment":true,"cached":false,"elapsed_ms":1.500,"error":null,"reason":null,"io_error":null}"#,
+		);
+
+		out.clear();
+		let record = Record {
+			path: Cow::Borrowed(Path::new("/foo/bar.html")),
+			result: Err(HtminlError::EmptyFile),
+			cached: false,
+			elapsed: Duration::ZERO,
+		};
+		record.push_json(&mut out);
+		assert_eq!(
+			out,
+			r#"{"path":"/foo/bar.html","before":null,"after":null,"changed":false,"fragment":null,"cached":false,"elapsed_ms":0.000,"error":"EmptyFile","reason":"The file is empty.","io_error":null}"#,
+		);
+
+		// I/O errors have more to say.
+		out.clear();
+		let err = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+		let record = Record {
+			path: Cow::Borrowed(Path::new("/foo/bar.html")),
+			result: Err(HtminlError::Save(ErrorContext::io(Path::new("/out/bar.html"), &err))),
+			cached: false,
+			elapsed: Duration::ZERO,
+		};
+		record.push_json(&mut out);
+		assert_eq!(
+			out,
+			r#"{"path":"/foo/bar.html","before":null,"after":null,"changed":false,"fragment":null,"cached":false,"elapsed_ms":0.000,"error":"Save","reason":"Unable to save document. /out/bar.html: permission denied","io_error":"PermissionDenied"}"#,
+		);
+	}
+}
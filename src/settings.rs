@@ -0,0 +1,371 @@
+/*!
+# HTMinL: Settings.
+*/
+
+use crate::{
+	Backup,
+	Configs,
+	Diff,
+	Discovery,
+	HtminlError,
+	Output,
+	Precompress,
+	Preserve,
+	Report,
+};
+use std::{
+	num::{
+		NonZeroU64,
+		NonZeroUsize,
+	},
+	path::PathBuf,
+	sync::Arc,
+};
+
+
+
+#[expect(clippy::struct_excessive_bools, reason = "They're independent flags.")]
+#[derive(Debug)]
+/// # Runtime Settings.
+///
+/// This holds the parsed and validated CLI arguments.
+pub(super) struct Settings {
+	/// # Backups.
+	pub(super) backup: Option<Backup>,
+
+	/// # Savings Breakdown?
+	pub(super) breakdown: bool,
+
+	/// # Incremental Cache Manifest.
+	pub(super) cache: Option<PathBuf>,
+
+	/// # Configuration Files.
+	pub(super) configs: Option<Arc<Configs>>,
+
+	/// # Discovery Rules.
+	pub(super) discovery: Discovery,
+
+	/// # Entry Paths.
+	///
+	/// The file and/or directory paths to crawl, as given.
+	pub(super) entries: Vec<PathBuf>,
+
+	/// # Thread Count.
+	///
+	/// This is the user's upper limit, if any.
+	pub(super) jobs: Option<NonZeroUsize>,
+
+	/// # Maximum File Size.
+	///
+	/// Documents larger than this (in bytes) are skipped.
+	pub(super) max_size: Option<NonZeroU64>,
+
+	/// # Output Destination.
+	pub(super) output: Output,
+
+	/// # Precompression.
+	pub(super) precompress: Option<Precompress>,
+
+	/// # Metadata Preservation.
+	pub(super) preserve: Preserve,
+
+	/// # Profile Phase Timings?
+	pub(super) profile: bool,
+
+	/// # Progress Bar?
+	pub(super) progress: bool,
+
+	/// # Report.
+	pub(super) report: Option<Report>,
+
+	/// # Restore Backups?
+	pub(super) restore: bool,
+
+	/// # STDIN/STDOUT Mode?
+	pub(super) stdin: bool,
+
+	/// # Strict Mode?
+	///
+	/// If true, skipped documents are treated as a fatal error.
+	pub(super) strict: bool,
+
+	/// # Top Documents.
+	///
+	/// The number of documents to list for each `--top` ranking, if any.
+	pub(super) top: Option<NonZeroUsize>,
+
+	/// # Watch Mode?
+	pub(super) watch: bool,
+}
+
+impl Settings {
+	#[expect(clippy::too_many_lines, reason = "There are a lot of options.")]
+	/// # From CLI.
+	///
+	/// Parse the CLI arguments.
+	///
+	/// ## Errors
+	///
+	/// This will return an error if any of the arguments are invalid or
+	/// conflict with one another, or if help/version information was
+	/// requested.
+	pub(super) fn new() -> Result<Self, HtminlError> {
+		argyle::argue! {
+			Backup        "--backup",
+			Breakdown     "--breakdown",
+			Check         "--check" "--dry-run",
+			Diff          "--diff",
+			DiffPretty    "--diff-pretty",
+			ExtOnly       "--ext-only",
+			Gitignore     "--gitignore",
+			Help     "-h" "--help",
+			IfChanged     "--if-changed",
+			Incremental   "--incremental",
+			NoConfig      "--no-config",
+			PreserveTimes "--preserve-times",
+			Profile       "--profile",
+			Progress "-p" "--progress",
+			Restore       "--restore",
+			Sniff         "--sniff",
+			Strict        "--strict",
+			Stdout        "--stdout",
+			Version  "-V" "--version",
+			Watch         "--watch",
+
+			@options
+			Cache         "--cache",
+			Exclude       "--exclude",
+			Ext           "--ext",
+			Include       "--include",
+			Jobs     "-j" "--jobs",
+			List     "-l" "--list",
+			MaxSize       "--max-file-size",
+			OutDir        "--out-dir",
+			Precompress   "--precompress",
+			Report        "--report",
+			Suffix        "--suffix",
+			Top           "--top",
+
+			@catchall-paths Path,
+		}
+
+		// Parse CLI arguments.
+		let mut backup = false;
+		let mut backup_dir = None;
+		let mut breakdown = false;
+		let mut restore = false;
+		let mut cache = None;
+		let mut check = false;
+		let mut diff = None;
+		let mut exts = Vec::new();
+		let mut ext_only = false;
+		let mut include = Vec::new();
+		let mut exclude = Vec::new();
+		let mut gitignore = false;
+		let mut if_changed = false;
+		let mut preserve_times = false;
+		let mut no_config = false;
+		let mut jobs = None;
+		let mut max_size = None;
+		let mut sniff = false;
+		let mut strict = false;
+		let mut profile = false;
+		let mut progress = false;
+		let mut stdin = false;
+		let mut watch = false;
+		let mut out_dir = None;
+		let mut suffix = None;
+		let mut top = None;
+		let mut report = None;
+		let mut precompress = None;
+		let mut entries = Vec::new();
+		for arg in Argument::args_os() {
+			match arg {
+				Argument::Backup =>   { backup = true; },
+				Argument::Breakdown => { breakdown = true; },
+				Argument::Check =>    { check = true; },
+				Argument::Diff =>     { diff.get_or_insert(Diff::Plain); },
+				Argument::DiffPretty => { diff.replace(Diff::Pretty); },
+				Argument::ExtOnly =>  { ext_only = true; },
+				Argument::Gitignore => { gitignore = true; },
+				Argument::Help =>     return Err(HtminlError::PrintHelp),
+				Argument::IfChanged => { if_changed = true; },
+				Argument::Incremental => {
+					cache.get_or_insert_with(|| PathBuf::from(crate::cache::DEFAULT_PATH));
+				},
+				Argument::NoConfig => { no_config = true; },
+				Argument::PreserveTimes => { preserve_times = true; },
+				Argument::Profile =>  { profile = true; },
+				Argument::Progress => { progress = true; },
+				Argument::Restore =>  { restore = true; },
+				Argument::Sniff =>    { sniff = true; },
+				Argument::Strict =>   { strict = true; },
+				Argument::Stdout =>   { stdin = true; },
+				Argument::Version =>  return Err(HtminlError::PrintVersion),
+				Argument::Watch =>    { watch = true; },
+
+				Argument::Cache(s) => { cache.replace(PathBuf::from(s)); },
+				Argument::Exclude(s) => { exclude.push(s); },
+				Argument::Ext(s) => { exts.push(s); },
+				Argument::Include(s) => { include.push(s); },
+				Argument::Jobs(s) => {
+					jobs.replace(s.trim().parse::<NonZeroUsize>().map_err(|_| HtminlError::Jobs)?);
+				},
+				Argument::List(s) => { read_list(&s, &mut entries)?; },
+				Argument::MaxSize(s) => {
+					max_size.replace(parse_size(&s).ok_or(HtminlError::MaxSize)?);
+				},
+				Argument::OutDir(s) => { out_dir.replace(PathBuf::from(s)); },
+				Argument::Precompress(s) => {
+					precompress.replace(Precompress::from_cli(&s).ok_or(HtminlError::Precompress)?);
+				},
+				Argument::Report(s) => {
+					report.replace(Report::from_cli(&s).ok_or(HtminlError::Report)?);
+				},
+				Argument::Suffix(s) => { suffix.replace(s); },
+				Argument::Top(s) => {
+					top.replace(s.trim().parse::<NonZeroUsize>().map_err(|_| HtminlError::Top)?);
+				},
+
+				Argument::Path(s) => { entries.push(PathBuf::from(s)); },
+
+				// A lone hyphen is shorthand for --stdout.
+				Argument::Other(s) if s == "-" => { stdin = true; },
+
+				// The backup directory is optional, so has to be joined.
+				Argument::Other(s) if s.starts_with("--backup=") => {
+					backup_dir.replace(PathBuf::from(&s[9..]));
+				},
+
+				// Mistake?
+				Argument::Other(s) =>   return Err(HtminlError::InvalidCli(s)),
+				Argument::OtherOs(s) => return Err(HtminlError::InvalidCli(s.to_string_lossy().into_owned())),
+			}
+		}
+
+		// STDIN/STDOUT mode only supports the one document.
+		if stdin && ! entries.is_empty() { return Err(HtminlError::StdinPaths); }
+
+		// Restoring isn't something that can be previewed.
+		if restore && (check || diff.is_some()) { return Err(HtminlError::RestoreConflict); }
+
+		// Watch mode requires inotify.
+		if watch && (stdin || ! cfg!(target_os = "linux")) { return Err(HtminlError::Watch); }
+
+		// What are we looking for?
+		let roots = crate::discover::roots(&entries);
+		let configs =
+			if no_config { None }
+			else { Some(Arc::new(Configs::load(roots.clone())?)) };
+		let discovery = Discovery::new(roots.clone())
+			.with_exts(&exts, ! ext_only)
+			.ok_or(HtminlError::Extension)?
+			.with_sniff(sniff)
+			.with_globs(&include, &exclude)
+			.ok_or(HtminlError::Glob)?
+			.with_ignore_files(gitignore)
+			.with_configs(configs.clone());
+
+		// Where are we backing things up?
+		let backup =
+			if let Some(dir) = backup_dir {
+				Some(Backup::dir(&dir, roots.clone()).ok_or(HtminlError::BackupDir)?)
+			}
+			else if backup || restore { Some(Backup::Sibling) }
+			else { None };
+
+		// Where are we saving things?
+		let output = match (out_dir, suffix) {
+			(Some(_), Some(_)) => return Err(HtminlError::OutputConflict),
+			_ if check || diff.is_some() => Output::DryRun(diff),
+			_ if stdin => Output::InPlace,
+			(Some(dir), None) => Output::dir(&dir, roots).ok_or(HtminlError::OutDir)?,
+			(None, Some(suffix)) => Output::sibling(&suffix).ok_or(HtminlError::Suffix)?,
+			(None, None) => Output::InPlace,
+		};
+
+		let preserve = Preserve::new(preserve_times, if_changed);
+
+		Ok(Self {
+			backup, breakdown, cache, configs, discovery, entries, jobs, max_size,
+			output, precompress, preserve, profile, progress, report, restore, stdin,
+			strict, top, watch,
+		})
+	}
+}
+
+
+
+/// # Parse Size.
+///
+/// Parse a byte size, optionally suffixed with a (binary) `K`, `M`, or `G`
+/// unit, e.g. `512K` or `50MiB`.
+///
+/// Returns `None` if the value is invalid or zero.
+fn parse_size(src: &str) -> Option<NonZeroU64> {
+	let src = src.trim().to_ascii_uppercase();
+	let src = src.strip_suffix("IB").or_else(|| src.strip_suffix('B')).unwrap_or(&src);
+	let (num, unit) = match src.as_bytes().last()? {
+		b'K' => (&src[..src.len() - 1], 1024_u64),
+		b'M' => (&src[..src.len() - 1], 1024 * 1024),
+		b'G' => (&src[..src.len() - 1], 1024 * 1024 * 1024),
+		_ => (src, 1),
+	};
+
+	num.trim().parse::<u64>().ok()
+		.and_then(|n| n.checked_mul(unit))
+		.and_then(NonZeroU64::new)
+}
+
+/// # Read List File.
+///
+/// Read (file and/or directory) paths from a text file — or STDIN if `"-"` —
+/// one per line, and add them to `entries`.
+fn read_list(src: &str, entries: &mut Vec<PathBuf>) -> Result<(), HtminlError> {
+	use std::io::IsTerminal;
+
+	let raw =
+		if src == "-" {
+			let stdin = std::io::stdin();
+			if stdin.is_terminal() { return Ok(()); }
+			std::io::read_to_string(stdin)
+		}
+		else { std::fs::read_to_string(src) }
+		.map_err(|_| HtminlError::ListFile)?;
+
+	for line in raw.lines() {
+		let line = line.trim();
+		if ! line.is_empty() { entries.push(PathBuf::from(line)); }
+	}
+
+	Ok(())
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn t_parse_size() {
+		for (raw, expected) in [
+			("100", Some(100)),
+			(" 100b ", Some(100)),
+			("1k", Some(1024)),
+			("3KiB", Some(3072)),
+			("2M", Some(2 * 1024 * 1024)),
+			("1G", Some(1024 * 1024 * 1024)),
+			("0", None),
+			("0K", None),
+			("", None),
+			("K", None),
+			("-1M", None),
+			("1.5", None),
+			("1T", None),
+			("99999999999G", None),
+		] {
+			assert_eq!(parse_size(raw).map(NonZeroU64::get), expected, "{raw:?}");
+		}
+	}
+}
@@ -0,0 +1,208 @@
+/*!
+# HTMinL: Top Documents.
+*/
+
+use crate::{
+	minify::Minified,
+	report::Record,
+};
+use dactyl::{
+	NicePercent,
+	NiceU64,
+};
+use fyi_msg::{
+	fyi_ansi::dim,
+	Msg,
+};
+use std::{
+	cmp::Ordering,
+	num::NonZeroUsize,
+	path::Path,
+};
+
+
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+/// # Ranking.
+///
+/// The different ways documents can be ranked for `--top`.
+enum Ranking {
+	/// # Most Bytes Saved.
+	Saved,
+
+	/// # Highest Percentage Saved.
+	Percent,
+
+	/// # Largest Output.
+	Largest,
+
+	/// # Unchanged (Largest First).
+	Unchanged,
+}
+
+impl Ranking {
+	/// # All Rankings.
+	const ALL: [Self; 4] = [Self::Saved, Self::Percent, Self::Largest, Self::Unchanged];
+
+	#[must_use]
+	/// # Label.
+	const fn label(self) -> &'static str {
+		match self {
+			Self::Saved => "Most bytes saved:",
+			Self::Percent => "Highest percentage saved:",
+			Self::Largest => "Largest output:",
+			Self::Unchanged => "Unchanged:",
+		}
+	}
+
+	#[must_use]
+	/// # Rank.
+	///
+	/// Return (up to) the top `n` documents for this ranking, best first.
+	/// Ties are broken by path to keep things predictable.
+	fn rank<'a>(self, docs: &[(&'a Path, Minified)], n: NonZeroUsize)
+	-> Vec<(&'a Path, Minified)> {
+		let mut out: Vec<_> = docs.iter()
+			.filter(|(_, m)| match self {
+				Self::Saved | Self::Percent => m.after.is_some_and(|a| a < m.before),
+				Self::Largest => true,
+				Self::Unchanged => m.after.is_none(),
+			})
+			.copied()
+			.collect();
+
+		out.sort_unstable_by(|(a_path, a), (b_path, b)| match self {
+			Self::Saved => saved(b).cmp(&saved(a)),
+			Self::Percent => cmp_percent(b, a),
+			Self::Largest | Self::Unchanged => b.after_or_before().cmp(&a.after_or_before()),
+		}.then_with(|| a_path.cmp(b_path)));
+
+		out.truncate(n.get());
+		out
+	}
+
+	#[must_use]
+	/// # Format Line.
+	fn line(self, src: &Path, m: Minified) -> String {
+		match self {
+			Self::Saved => format!(
+				concat!("{} ", dim!("({} bytes)")),
+				src.display(),
+				NiceU64::from(saved(&m)),
+			),
+			Self::Percent => format!(
+				concat!("{} ", dim!("({})")),
+				src.display(),
+				NicePercent::from((saved(&m), m.before.get())),
+			),
+			Self::Largest | Self::Unchanged => format!(
+				concat!("{} ", dim!("({} bytes)")),
+				src.display(),
+				NiceU64::from(m.after_or_before()),
+			),
+		}
+	}
+}
+
+
+
+/// # Summarize.
+///
+/// Print the top `n` documents by bytes saved, percentage saved, and output
+/// size, along with the largest of those left unchanged.
+///
+/// Only freshly crunched documents are considered; those skipped as
+/// unchanged since the last run have no new figures to compare.
+pub(super) fn summarize(records: &[Record<'_>], n: NonZeroUsize) {
+	let docs: Vec<(&Path, Minified)> = records.iter()
+		.filter_map(|r|
+			if r.cached { None }
+			else { r.result.as_ref().ok().map(|m| (r.path.as_ref(), *m)) }
+		)
+		.collect();
+
+	for ranking in Ranking::ALL {
+		let top = ranking.rank(&docs, n);
+		if top.is_empty() { continue; }
+
+		crate::eprint(Msg::info(ranking.label()));
+		for (src, m) in top {
+			crate::eprint(
+				Msg::from(ranking.line(src, m)).with_indent(1).with_newline(true)
+			);
+		}
+	}
+}
+
+
+
+#[must_use]
+/// # Bytes Saved.
+///
+/// Minification can occasionally make a document _bigger_ — implied tags,
+/// etc. — in which case nothing was saved.
+const fn saved(m: &Minified) -> u64 {
+	m.before.get().saturating_sub(m.after_or_before().get())
+}
+
+#[must_use]
+/// # Compare Percentage Saved.
+///
+/// Compare the savings ratios of `a` and `b` without resorting to floats.
+fn cmp_percent(a: &Minified, b: &Minified) -> Ordering {
+	let lhs = u128::from(saved(a)) * u128::from(b.before.get());
+	let rhs = u128::from(saved(b)) * u128::from(a.before.get());
+	lhs.cmp(&rhs)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Savings;
+	use std::num::NonZeroU64;
+
+	/// # Test Document.
+	fn doc(before: u64, after: Option<u64>) -> Minified {
+		Minified {
+			before: NonZeroU64::new(before).expect("Zero before."),
+			after: after.and_then(NonZeroU64::new),
+			fragment: false,
+			savings: Savings::default(),
+			timings: crate::Timings::default(),
+		}
+	}
+
+	#[test]
+	fn t_rank() {
+		let docs = [
+			(Path::new("/a.html"), doc(1000, Some(900))), // 100, 10%
+			(Path::new("/b.html"), doc(100, Some(50))),   // 50, 50%
+			(Path::new("/c.html"), doc(5000, None)),
+			(Path::new("/d.html"), doc(2000, Some(1900))), // 100, 5%
+			(Path::new("/e.html"), doc(10, None)),
+			(Path::new("/f.html"), doc(50, Some(70))),     // Grew!
+		];
+		let two = NonZeroUsize::new(2).unwrap();
+		let paths = |ranking: Ranking| -> Vec<&str> {
+			ranking.rank(&docs, two).into_iter()
+				.filter_map(|(p, _)| p.to_str())
+				.collect()
+		};
+
+		assert_eq!(paths(Ranking::Saved), ["/a.html", "/d.html"]);
+		assert_eq!(paths(Ranking::Percent), ["/b.html", "/a.html"]);
+		assert_eq!(paths(Ranking::Largest), ["/c.html", "/d.html"]);
+		assert_eq!(paths(Ranking::Unchanged), ["/c.html", "/e.html"]);
+		assert_eq!(saved(&docs[5].1), 0);
+
+		// Growers shouldn't rank for savings, no matter how many slots.
+		let all = NonZeroUsize::new(10).unwrap();
+		assert!(Ranking::Saved.rank(&docs, all).iter().all(|(p, _)| *p != Path::new("/f.html")));
+		assert!(Ranking::Percent.rank(&docs, all).iter().all(|(p, _)| *p != Path::new("/f.html")));
+
+		// Fewer than N.
+		assert_eq!(Ranking::Unchanged.rank(&docs, NonZeroUsize::new(5).unwrap()).len(), 2);
+	}
+}
@@ -0,0 +1,245 @@
+/*!
+# HTMinL: Watch Mode.
+*/
+
+use std::{
+	collections::HashMap,
+	path::{
+		Path,
+		PathBuf,
+	},
+	sync::Mutex,
+	time::SystemTime,
+};
+
+
+
+#[derive(Debug, Default)]
+/// # Written Files.
+///
+/// This keeps track of the size and modification time of each file HTMinL
+/// has written while watching, so the resulting filesystem events can be
+/// told apart from genuine changes.
+pub(super) struct Written(Mutex<HashMap<PathBuf, (u64, SystemTime)>>);
+
+impl Written {
+	/// # Add.
+	///
+	/// Record the current state of `src`.
+	pub(super) fn add(&self, src: &Path) {
+		if
+			let Some(state) = state(src) &&
+			let Ok(mut written) = self.0.lock()
+		{
+			written.insert(src.to_path_buf(), state);
+		}
+	}
+
+	#[cfg(target_os = "linux")]
+	#[must_use]
+	/// # Take.
+	///
+	/// Returns `true` if `src` is exactly as HTMinL last left it.
+	///
+	/// Either way, the record is removed; each write only triggers the one
+	/// event, so there's no reason to keep it around afterwards.
+	fn take(&self, src: &Path) -> bool {
+		self.0.lock().ok()
+			.and_then(|mut written| written.remove(src))
+			.is_some_and(|old| state(src) == Some(old))
+	}
+}
+
+
+
+#[cfg(target_os = "linux")]
+/// # Watch.
+///
+/// Watch the `entries` directories — recursively — for new or modified files,
+/// sending any that pass the `filter` to the workers via `tx`, until the
+/// process is killed.
+///
+/// Files written by HTMinL itself are ignored, as is the output directory,
+/// if any.
+///
+/// ## Errors
+///
+/// This will return an error if inotify cannot be initialized or read.
+pub(super) fn watch<F>(
+	entries: &[PathBuf],
+	out_dir: Option<&Path>,
+	written: &Written,
+	killed: &std::sync::atomic::AtomicBool,
+	mut filter: F,
+	tx: &flume::Sender<std::borrow::Cow<'_, Path>>,
+) -> Result<(), crate::HtminlError>
+where F: FnMut(&Path) -> bool {
+	use crate::HtminlError;
+	use fyi_msg::Msg;
+	use inotify::{
+		EventMask,
+		Inotify,
+	};
+	use std::{
+		borrow::Cow,
+		sync::atomic::Ordering::SeqCst,
+		time::Duration,
+	};
+
+	let mut inotify = Inotify::init().map_err(|_| HtminlError::Watch)?;
+	let mut dirs = HashMap::new();
+	for dir in entries {
+		if let Ok(dir) = std::fs::canonicalize(dir) && dir.is_dir() {
+			add_dirs(&inotify, &mut dirs, dir, out_dir);
+		}
+	}
+	if dirs.is_empty() { return Err(HtminlError::Watch); }
+
+	crate::eprint(Msg::info("Watching for changes… (Press CTRL+C to quit.)"));
+
+	let mut buf = [0_u8; 4096];
+	let mut files = Vec::new();
+	let mut new_dirs = Vec::new();
+	while ! killed.load(SeqCst) {
+		match inotify.read_events(&mut buf) {
+			Ok(events) => {
+				for e in events {
+					if e.mask.contains(EventMask::IGNORED) {
+						dirs.remove(&e.wd);
+						continue;
+					}
+
+					let (Some(dir), Some(name)) = (dirs.get(&e.wd), e.name) else { continue; };
+					let path = dir.join(name);
+					if e.mask.contains(EventMask::ISDIR) {
+						if e.mask.intersects(EventMask::CREATE | EventMask::MOVED_TO) {
+							new_dirs.push(path);
+						}
+					}
+					else if e.mask.intersects(EventMask::CLOSE_WRITE | EventMask::MOVED_TO) {
+						files.push(path);
+					}
+				}
+			},
+			Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+				std::thread::sleep(Duration::from_millis(100));
+				continue;
+			},
+			Err(_) => return Err(HtminlError::Watch),
+		}
+
+		// New directories need watching, and may already have stuff in them.
+		for dir in std::mem::take(&mut new_dirs) {
+			files.extend(dowser::Dowser::from(dir.as_path()));
+			add_dirs(&inotify, &mut dirs, dir, out_dir);
+		}
+
+		// Send the files off for crunching!
+		files.sort_unstable();
+		files.dedup();
+		for file in std::mem::take(&mut files) {
+			if
+				! out_dir.is_some_and(|d| file.starts_with(d)) &&
+				! written.take(&file) &&
+				filter(&file) &&
+				tx.send(Cow::Owned(file)).is_err()
+			{
+				return Err(HtminlError::JobServer);
+			}
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+/// # Watch (Unsupported).
+///
+/// Watch mode requires inotify, so is only available on Linux.
+///
+/// ## Errors
+///
+/// This always returns an error.
+pub(super) fn watch<F>(
+	_entries: &[PathBuf],
+	_out_dir: Option<&Path>,
+	_written: &Written,
+	_killed: &std::sync::atomic::AtomicBool,
+	_filter: F,
+	_tx: &flume::Sender<std::borrow::Cow<'_, Path>>,
+) -> Result<(), crate::HtminlError>
+where F: FnMut(&Path) -> bool {
+	Err(crate::HtminlError::Watch)
+}
+
+
+
+#[cfg(target_os = "linux")]
+/// # Add Directories.
+///
+/// Recursively add inotify watches for `dir` and its subdirectories, skipping
+/// symlinks and the output directory, if any.
+fn add_dirs(
+	inotify: &inotify::Inotify,
+	dirs: &mut HashMap<inotify::WatchDescriptor, PathBuf>,
+	dir: PathBuf,
+	out_dir: Option<&Path>,
+) {
+	use inotify::WatchMask;
+
+	let mut stack = vec![dir];
+	while let Some(dir) = stack.pop() {
+		if out_dir.is_some_and(|d| dir.starts_with(d)) { continue; }
+
+		let mask = WatchMask::CLOSE_WRITE | WatchMask::CREATE | WatchMask::MOVED_TO |
+			WatchMask::DONT_FOLLOW | WatchMask::ONLYDIR;
+		let Ok(wd) = inotify.watches().add(&dir, mask) else { continue; };
+
+		if let Ok(iter) = std::fs::read_dir(&dir) {
+			stack.extend(iter.filter_map(|e| {
+				let e = e.ok()?;
+				if e.file_type().ok()?.is_dir() { Some(e.path()) }
+				else { None }
+			}));
+		}
+
+		dirs.insert(wd, dir);
+	}
+}
+
+/// # File State.
+///
+/// Return the size and modification time of `src`.
+fn state(src: &Path) -> Option<(u64, SystemTime)> {
+	let meta = std::fs::metadata(src).ok()?;
+	Some((meta.len(), meta.modified().ok()?))
+}
+
+
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn t_written() {
+		let dir = std::env::temp_dir().join(format!("htminl-watch-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).expect("Unable to create temp dir.");
+		let src = dir.join("index.html");
+		std::fs::write(&src, b"<p>Hello</p>").expect("Unable to write source.");
+
+		// Our writes are ours, but only the once.
+		let written = Written::default();
+		written.add(&src);
+		assert!(written.take(&src));
+		assert!(! written.take(&src));
+
+		// Other changes aren't ours, but still clear the record.
+		written.add(&src);
+		std::fs::write(&src, b"<p>Goodbye</p>").expect("Unable to write source.");
+		assert!(! written.take(&src));
+		assert!(written.0.lock().is_ok_and(|w| w.is_empty()), "Written should be empty.");
+
+		let _res = std::fs::remove_dir_all(&dir);
+	}
+}